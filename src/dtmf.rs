@@ -0,0 +1,24 @@
+use crate::Key;
+
+const ROW_FREQUENCIES: [u16; 4] = [697, 770, 852, 941];
+const COLUMN_FREQUENCIES: [u16; 3] = [1209, 1336, 1477];
+
+/// Returns the standard DTMF tone pair for a key as `(low, high)` frequencies in Hz. The low
+/// tone comes from the key's row and the high tone from its column.
+pub fn dtmf_frequencies(key: Key) -> (u16, u16) {
+    let (row, column) = match key {
+        Key::One => (0, 0),
+        Key::Two => (0, 1),
+        Key::Three => (0, 2),
+        Key::Four => (1, 0),
+        Key::Five => (1, 1),
+        Key::Six => (1, 2),
+        Key::Seven => (2, 0),
+        Key::Eight => (2, 1),
+        Key::Nine => (2, 2),
+        Key::Star => (3, 0),
+        Key::Zero => (3, 1),
+        Key::Hash => (3, 2),
+    };
+    (ROW_FREQUENCIES[row], COLUMN_FREQUENCIES[column])
+}
@@ -0,0 +1,79 @@
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::{KeyEvent, Keypad, PortColumns, RowPins};
+
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Keypad<R, C, B, S> {
+    /**
+    Returns an iterator over the events from [`poll`](Keypad::poll), for processing them with
+    iterator adapters. Each call to `next` polls until there's an event. Without an idle
+    timeout the iterator never ends, so it needs an adapter such as `take_while` to stop it.
+
+    The iterator borrows both the keypad and the delay, so neither can be used until it's
+    dropped. While the keypad is suspended, `next` returns `None` straight away.
+
+    ```ignore
+    keypad
+        .events(&mut delay)
+        .with_idle_timeout_ms(5000)
+        .filter_map(|event| match event {
+            KeyEvent::Pressed { key, .. } => Some(key),
+            _ => None,
+        })
+        .take_while(|&key| key != '#')
+        .for_each(|key| display.push(key));
+    ```
+    */
+    pub fn events<'a>(&'a mut self, delay: &'a mut dyn DelayMs<u16>) -> Events<'a, R, C, B, S> {
+        Events {
+            keypad: self,
+            delay,
+            idle_timeout_ms: None,
+        }
+    }
+}
+
+/// The events from polling a keypad, returned by [`Keypad::events`]
+pub struct Events<'a, R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> {
+    keypad: &'a mut Keypad<R, C, B, S>,
+    delay: &'a mut dyn DelayMs<u16>,
+    idle_timeout_ms: Option<u32>,
+}
+
+impl<'a, R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Events<'a, R, C, B, S> {
+    /// Makes `next` return `None` once it has polled for `timeout_ms` without an event, which
+    /// ends most adapters. The time is measured the same way as [`poll`](Keypad::poll) measures
+    /// how long keys are held, but with each poll counting as at least 1ms, so a settle time of
+    /// zero still times out. It starts again with each call, so the iterator can carry on after
+    /// timing out.
+    pub fn with_idle_timeout_ms(mut self, timeout_ms: u32) -> Self {
+        self.idle_timeout_ms = Some(timeout_ms);
+        self
+    }
+}
+
+impl<'a, R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Iterator
+    for Events<'a, R, C, B, S>
+{
+    type Item = KeyEvent;
+
+    fn next(&mut self) -> Option<KeyEvent> {
+        let mut idle_ms: u32 = 0;
+        loop {
+            if self.keypad.is_suspended() {
+                return None;
+            }
+            let started_ms = self.keypad.state.clock_ms;
+            if let Some(event) = self.keypad.poll(self.delay) {
+                return Some(event);
+            }
+            // A read with no settle time still counts, so the timeout can't be missed
+            let elapsed_ms = self.keypad.state.clock_ms.wrapping_sub(started_ms);
+            idle_ms = idle_ms.saturating_add(elapsed_ms.max(1));
+            match self.idle_timeout_ms {
+                Some(timeout_ms) if idle_ms >= timeout_ms => return None,
+                _ => {}
+            }
+        }
+    }
+}
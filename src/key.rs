@@ -0,0 +1,119 @@
+use core::fmt;
+
+/// One of the keys on a telephone-style keypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    /// The `0` key
+    Zero,
+    /// The `1` key
+    One,
+    /// The `2` key
+    Two,
+    /// The `3` key
+    Three,
+    /// The `4` key
+    Four,
+    /// The `5` key
+    Five,
+    /// The `6` key
+    Six,
+    /// The `7` key
+    Seven,
+    /// The `8` key
+    Eight,
+    /// The `9` key
+    Nine,
+    /// The `*` key
+    Star,
+    /// The `#` key
+    Hash,
+}
+
+impl Key {
+    /// Returns the key labelled with this character, or `None` if no key has that label
+    pub fn from_char(c: char) -> Option<Key> {
+        match c {
+            '0' => Some(Key::Zero),
+            '1' => Some(Key::One),
+            '2' => Some(Key::Two),
+            '3' => Some(Key::Three),
+            '4' => Some(Key::Four),
+            '5' => Some(Key::Five),
+            '6' => Some(Key::Six),
+            '7' => Some(Key::Seven),
+            '8' => Some(Key::Eight),
+            '9' => Some(Key::Nine),
+            '*' => Some(Key::Star),
+            '#' => Some(Key::Hash),
+            _ => None,
+        }
+    }
+
+    /// Returns the character printed on the key
+    pub fn to_char(self) -> char {
+        match self {
+            Key::Zero => '0',
+            Key::One => '1',
+            Key::Two => '2',
+            Key::Three => '3',
+            Key::Four => '4',
+            Key::Five => '5',
+            Key::Six => '6',
+            Key::Seven => '7',
+            Key::Eight => '8',
+            Key::Nine => '9',
+            Key::Star => '*',
+            Key::Hash => '#',
+        }
+    }
+}
+
+impl From<Key> for char {
+    fn from(key: Key) -> char {
+        key.to_char()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+/// Returns a compact code for the key that won't change between versions of this crate, for
+/// sending keys over a wire. Digits are `0` through `9`, `*` is `10` and `#` is `11`.
+pub fn scancode(key: Key) -> u8 {
+    match key {
+        Key::Zero => 0,
+        Key::One => 1,
+        Key::Two => 2,
+        Key::Three => 3,
+        Key::Four => 4,
+        Key::Five => 5,
+        Key::Six => 6,
+        Key::Seven => 7,
+        Key::Eight => 8,
+        Key::Nine => 9,
+        Key::Star => 10,
+        Key::Hash => 11,
+    }
+}
+
+/// Returns the key for a code from [`scancode`], or `None` if no key has that code
+pub fn from_scancode(code: u8) -> Option<Key> {
+    match code {
+        0 => Some(Key::Zero),
+        1 => Some(Key::One),
+        2 => Some(Key::Two),
+        3 => Some(Key::Three),
+        4 => Some(Key::Four),
+        5 => Some(Key::Five),
+        6 => Some(Key::Six),
+        7 => Some(Key::Seven),
+        8 => Some(Key::Eight),
+        9 => Some(Key::Nine),
+        10 => Some(Key::Star),
+        11 => Some(Key::Hash),
+        _ => None,
+    }
+}
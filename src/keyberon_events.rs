@@ -0,0 +1,38 @@
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+use keyberon::layout::Event;
+
+use crate::{CellEvent, CellEvents, Keypad, PortColumns, RowPins};
+
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Keypad<R, C, B, S> {
+    /**
+    Scans the keypad once and returns a keyberon event for each key pressed or released, so the
+    keypad can be the matrix behind a keyberon layout. Events use the key's `(row, col)` as
+    their coordinates, and any number of keys can be down at once. This is
+    [`poll_matrix`](Keypad::poll_matrix) with keyberon's events, and shares its state.
+
+    ```ignore
+    for event in keypad.keyberon_events(&mut delay) {
+        layout.event(event);
+    }
+    ```
+    */
+    pub fn keyberon_events(&mut self, delay: &mut dyn DelayMs<u16>) -> KeyberonEvents {
+        KeyberonEvents(self.poll_matrix(delay))
+    }
+}
+
+/// The keyberon events from one scan, returned by [`Keypad::keyberon_events`]
+#[derive(Clone, Debug)]
+pub struct KeyberonEvents(CellEvents);
+
+impl Iterator for KeyberonEvents {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        match self.0.next()? {
+            CellEvent::Pressed { row, col } => Some(Event::Press(row as u8, col as u8)),
+            CellEvent::Released { row, col } => Some(Event::Release(row as u8, col as u8)),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::{KeyEvent, Keypad, PortColumns, RowPins};
+
+/// Scans several independent keypads of the same type in turn, one keypad per call, so each
+/// call only blocks for a single scan
+///
+/// Every keypad keeps its own held-key state and options. This is for separate keypads, each
+/// with its own pins.
+pub struct KeypadSet<T, const K: usize> {
+    keypads: [T; K],
+    next: usize,
+}
+
+impl<T, const K: usize> KeypadSet<T, K> {
+    /// Create a new instance that scans these keypads, starting with the first
+    pub fn new(keypads: [T; K]) -> Self {
+        Self { keypads, next: 0 }
+    }
+
+    /// Returns the keypad at `index`, or `None` if there isn't one
+    pub fn keypad_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.keypads.get_mut(index)
+    }
+
+    // Returns the index of the keypad to scan next, and moves on to the one after it
+    fn advance(&mut self) -> Option<usize> {
+        if K == 0 {
+            return None;
+        }
+        let index = self.next;
+        self.next = (index + 1) % K;
+        Some(index)
+    }
+}
+
+impl<R, C, B, S, const K: usize> KeypadSet<Keypad<R, C, B, S>, K>
+where
+    R: RowPins,
+    C: PortColumns,
+    B: OutputPin,
+    S: OutputPin,
+{
+    /// Polls the next keypad in turn with [`Keypad::poll`], returning its index along with any
+    /// event. Since each keypad is only scanned every `K` calls, options that depend on how long
+    /// a key is held are better served by [`poll_next_at`](Self::poll_next_at).
+    pub fn poll_next(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<(usize, KeyEvent)> {
+        let index = self.advance()?;
+        self.keypads[index].poll(delay).map(|event| (index, event))
+    }
+
+    /// Same as [`poll_next`](Self::poll_next), but polls with [`Keypad::poll_at`] using
+    /// `now_ms` as the current time
+    pub fn poll_next_at(
+        &mut self,
+        delay: &mut dyn DelayMs<u16>,
+        now_ms: u32,
+    ) -> Option<(usize, KeyEvent)> {
+        let index = self.advance()?;
+        self.keypads[index]
+            .poll_at(delay, now_ms)
+            .map(|event| (index, event))
+    }
+}
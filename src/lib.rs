@@ -5,7 +5,7 @@ Provides a driver for reading from standard 3X4 keypads
 
 ## Example
 
-```rust
+```ignore
 let rows = (
     gpiob.pb15.into_pull_up_input(&mut gpiob.crh),
     gpioa.pa7.into_pull_up_input(&mut gpioa.crl),
@@ -29,113 +29,2256 @@ if key != ' ' {
 */
 #![no_std]
 
-use embedded_hal::digital::v2::{InputPin, OutputPin};
+use core::convert::Infallible;
 use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+#[cfg(feature = "async")]
+use embedded_hal_async::delay::DelayNs;
+
+mod key;
+pub use key::{from_scancode, scancode, Key};
+
+mod keypad_set;
+pub use keypad_set::KeypadSet;
+
+mod sequence;
+pub use sequence::{Matched, Sequence};
+
+mod events;
+pub use events::Events;
+
+mod matrix;
+use matrix::CellDebouncer;
+pub use matrix::{CellEvent, CellEvents};
+
+#[cfg(test)]
+mod tests;
+
+#[cfg(feature = "dtmf")]
+mod dtmf;
+#[cfg(feature = "dtmf")]
+pub use dtmf::dtmf_frequencies;
+
+#[cfg(feature = "keyberon")]
+mod keyberon_events;
+#[cfg(feature = "keyberon")]
+pub use keyberon_events::KeyberonEvents;
+
+/// Defines a type that makes it easier to supply the four pins required for rows in the keypad.
+/// These pins need to support the `embedded_hal::digital::v2::InputPin` trait
+pub type Rows<R0, R1, R2, R3> = (R0, R1, R2, R3);
+
+/// Defines a type that makes it easier to supply the four pins required for rows in the keypad
+/// These pins need to support the `embedded_hal::digital::v2::OutputPin` trait
+pub type Columns<C0, C1, C2> = (C0, C1, C2);
+
+/// Drives all of the column pins in one operation
+///
+/// Implement this for a GPIO port (or port expander) where a single register write is cheaper
+/// than driving each pin separately. The [`Columns`] tuple, and shorter tuples for keypads with
+/// fewer columns, implement it by setting each pin on its own.
+pub trait PortColumns {
+    /// Sets every column at once. Bit `n` of `pattern` drives column `n` high, and a clear bit
+    /// drives it low, which normally selects that column for reading. Bits above the third are
+    /// ignored.
+    fn write_columns(&mut self, pattern: u8);
+
+    /// Changes the columns from the `previous` pattern to `pattern`. By default this writes the
+    /// whole pattern, but implementations that drive pins individually can touch only the pins
+    /// that change.
+    fn update_columns(&mut self, previous: u8, pattern: u8) {
+        let _ = previous;
+        self.write_columns(pattern);
+    }
+}
+
+/// Reads all of the row pins at once
+///
+/// Tuples of up to four [`InputPin`]s implement this, and the pins don't need to be the same
+/// type. Keypads with fewer than four rows can use a shorter tuple.
+pub trait RowPins {
+    /// Returns a bit set for each row that reads low, with bit `n` for row `n`. Bits above the
+    /// fourth are ignored.
+    fn read_rows(&self) -> u8;
+}
+
+// Implements RowPins for a tuple of input pins
+macro_rules! row_pins {
+    ($($pin:ident $index:tt),+) => {
+        impl<$($pin: InputPin),+> RowPins for ($($pin,)+) {
+            fn read_rows(&self) -> u8 {
+                let mut res = 0;
+                $(
+                    if self.$index.is_low().unwrap_or_default() {
+                        res |= 1 << $index;
+                    }
+                )+
+                res
+            }
+        }
+    };
+}
+
+row_pins!(R0 0);
+row_pins!(R0 0, R1 1);
+row_pins!(R0 0, R1 1, R2 2);
+row_pins!(R0 0, R1 1, R2 2, R3 3);
+
+// Implements PortColumns for a tuple of output pins, changing only the pins whose level
+// differs from the previous pattern
+macro_rules! port_columns {
+    ($($pin:ident $index:tt),+) => {
+        impl<$($pin: OutputPin),+> PortColumns for ($($pin,)+) {
+            fn write_columns(&mut self, pattern: u8) {
+                self.update_columns(!pattern, pattern);
+            }
+
+            fn update_columns(&mut self, previous: u8, pattern: u8) {
+                let changed = previous ^ pattern;
+                $(
+                    if changed & (1 << $index) != 0 {
+                        set_level(&mut self.$index, pattern & (1 << $index) != 0);
+                    }
+                )+
+            }
+        }
+    };
+}
+
+port_columns!(C0 0);
+port_columns!(C0 0, C1 1);
+port_columns!(C0 0, C1 1, C2 2);
+
+/**
+Selects columns through a decoder or demultiplexer, such as a 74HC139, rather than with a pin
+for each column
+
+`set_column` is called with the index of the column to select, or `None` to select none, for
+example by disabling the decoder. It can own the pins it needs:
+
+```ignore
+let columns = DemuxColumns::new(move |column: Option<usize>| match column {
+    Some(index) => {
+        a0.set_state(PinState::from(index & 1 != 0)).ok();
+        a1.set_state(PinState::from(index & 2 != 0)).ok();
+        enable.set_low().ok();
+    }
+    None => {
+        enable.set_high().ok();
+    }
+});
+let mut keypad = Keypad::new(rows, columns);
+```
+
+A decoder selects one column at a time, so [`Keypad::arm_wake`], which selects every column,
+selects none instead. For the same reason this can't be used with
+[`ScanStrategy::ReleaseOne`], which would read no keys at all. Don't combine this with the
+config's `inverted` setting.
+*/
+pub struct DemuxColumns<F: FnMut(Option<usize>)> {
+    set_column: F,
+}
+
+impl<F: FnMut(Option<usize>)> DemuxColumns<F> {
+    /// Create a new instance that selects columns by calling `set_column`
+    pub fn new(set_column: F) -> Self {
+        Self { set_column }
+    }
+}
+
+impl<F: FnMut(Option<usize>)> PortColumns for DemuxColumns<F> {
+    fn write_columns(&mut self, pattern: u8) {
+        let selected = !pattern & COLUMNS_RELEASED;
+        let column = if selected.count_ones() == 1 {
+            Some(selected.trailing_zeros() as usize)
+        } else {
+            None
+        };
+        (self.set_column)(column);
+    }
+}
+
+fn set_level<P: OutputPin>(pin: &mut P, high: bool) {
+    if high {
+        pin.set_high().unwrap_or_default();
+    } else {
+        pin.set_low().unwrap_or_default();
+    }
+}
+
+/// Manages the pins and the logic for scanning a keypad
+///
+/// The keypad owns its pins and remembers state between calls, such as which key
+/// [`poll`](Keypad::poll) last saw held, but it never holds a delay. Every method that waits
+/// takes the delay as an argument, so different calls can use different delays, for example a
+/// busy-wait delay during start-up and a timer-based one afterwards.
+///
+/// The functions it calls, such as [`on_press`](Keypad::on_press) and
+/// [`on_scan`](Keypad::on_scan), are plain `fn` pointers rather than closures, so setting them
+/// doesn't change the keypad's type and every keypad in a [`KeypadSet`] can have its own.
+/// A closure that doesn't capture anything converts to one. State they update, such as a
+/// count of presses, has to live in a `static`, for example behind a critical-section mutex.
+pub struct Keypad<R: RowPins, C: PortColumns, B: OutputPin = NoPin, S: OutputPin = NoPin> {
+    rows: R,
+    columns: C,
+    backlight: B,
+    strobe: S,
+    state: State,
+}
+
+// Everything the keypad keeps besides its pins
+struct State {
+    column_pattern: Option<u8>,
+    settle_ms: u16,
+    active_high_rows: u8,
+    inverted: bool,
+    keymap: Keymap,
+    codes: [[u8; 3]; 4],
+    active_columns: u8,
+    scan_order: [u8; COLS],
+    active_rows: u8,
+    unfiltered_raw: u16,
+    stray_mask: u16,
+    held: Option<char>,
+    held_since_ms: u32,
+    press_reported: bool,
+    clock_ms: u32,
+    confirm_hold_ms: u32,
+    min_repress_ms: u32,
+    press_suppressed: bool,
+    last_release: Option<(char, u32)>,
+    on_press: Option<fn(char)>,
+    on_release: Option<fn(char)>,
+    on_invalid: Option<fn(u16)>,
+    on_scan: Option<fn(&[[bool; COLS]; ROWS])>,
+    idle_state: IdleState,
+    scan_strategy: ScanStrategy,
+    multi_key_policy: MultiKeyPolicy,
+    digits_only: bool,
+    charge_flush: bool,
+    last_key: Option<char>,
+    lock_on_first: bool,
+    macros: [Option<(char, &'static [char])>; MACRO_SLOTS],
+    pending: &'static [char],
+    longpress_map: &'static [(char, char)],
+    longpress_ms: u32,
+    longpress_pending: bool,
+    reported_as: char,
+    queued: Option<KeyEvent>,
+    retries: u8,
+    backlight_active_low: bool,
+    strobe_active_low: bool,
+    release_candidate: Option<char>,
+    release_cancelled: bool,
+    phase: ScanPhase,
+    phase_since_ms: u32,
+    phase_step: usize,
+    phase_raw: u16,
+    debouncer: Debouncer,
+    suspended: bool,
+    cells: CellDebouncer,
+    encoder: Option<Encoder>,
+    critical_section: Option<fn(&mut dyn FnMut())>,
+}
+
+impl State {
+    // Switches to a new keymap, along with the codes read_u8 looks up
+    fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+        self.codes = keymap_codes(&keymap);
+    }
+
+    fn new() -> Self {
+        Self {
+            column_pattern: None,
+            settle_ms: 1,
+            active_high_rows: 0,
+            inverted: false,
+            keymap: DEFAULT_KEYMAP,
+            codes: keymap_codes(&DEFAULT_KEYMAP),
+            active_columns: COLUMNS_RELEASED,
+            scan_order: [0, 1, 2],
+            active_rows: ALL_ROWS as u8,
+            unfiltered_raw: 0,
+            stray_mask: 0,
+            held: None,
+            held_since_ms: 0,
+            press_reported: false,
+            clock_ms: 0,
+            confirm_hold_ms: 0,
+            min_repress_ms: 0,
+            press_suppressed: false,
+            last_release: None,
+            on_press: None,
+            on_release: None,
+            on_invalid: None,
+            on_scan: None,
+            idle_state: IdleState::Released,
+            scan_strategy: ScanStrategy::DriveOne,
+            multi_key_policy: MultiKeyPolicy::Reject,
+            digits_only: false,
+            charge_flush: false,
+            last_key: None,
+            lock_on_first: false,
+            macros: [None; MACRO_SLOTS],
+            pending: &[],
+            longpress_map: &[],
+            longpress_ms: 0,
+            longpress_pending: false,
+            reported_as: ' ',
+            queued: None,
+            retries: 0,
+            backlight_active_low: false,
+            strobe_active_low: false,
+            release_candidate: None,
+            release_cancelled: false,
+            phase: ScanPhase::Idle,
+            phase_since_ms: 0,
+            phase_step: 0,
+            phase_raw: 0,
+            debouncer: Debouncer::new(),
+            suspended: false,
+            cells: CellDebouncer::new(),
+            encoder: None,
+            critical_section: None,
+        }
+    }
+}
+
+// Waits for a number of identical scans in a row before accepting a raw value, and keeps
+// statistics on how long each key took to settle
+struct Debouncer {
+    samples: u8,
+    candidate: u16,
+    run: u8,
+    scans: u8,
+    last_scans: u8,
+    stable: u16,
+    stats: [[BounceStats; 3]; 4],
+    #[cfg(feature = "bounce-capture")]
+    capture: [u16; BOUNCE_CAPTURE],
+    #[cfg(feature = "bounce-capture")]
+    captured: usize,
+}
+
+impl Debouncer {
+    fn new() -> Self {
+        Self {
+            samples: 1,
+            candidate: 0,
+            run: 0,
+            scans: 0,
+            last_scans: 0,
+            stable: 0,
+            stats: [[BounceStats::default(); 3]; 4],
+            #[cfg(feature = "bounce-capture")]
+            capture: [0; BOUNCE_CAPTURE],
+            #[cfg(feature = "bounce-capture")]
+            captured: 0,
+        }
+    }
+
+    // Takes the raw value of one scan, returning the debounced value once enough scans agree.
+    // A value that won't settle within DEBOUNCE_LIMIT times the required samples reads as no
+    // keys.
+    fn step(&mut self, raw: u16) -> Option<u16> {
+        #[cfg(feature = "bounce-capture")]
+        self.capture_scan(raw);
+        self.scans = self.scans.saturating_add(1);
+        if self.run > 0 && raw == self.candidate {
+            self.run = self.run.saturating_add(1);
+        } else {
+            self.candidate = raw;
+            self.run = 1;
+        }
+
+        let stable = if self.run >= self.samples {
+            self.candidate
+        } else if self.scans >= self.samples.saturating_mul(DEBOUNCE_LIMIT) {
+            0
+        } else {
+            return None;
+        };
+
+        let pressed = stable & !self.stable;
+        for bit in (0..12).filter(|bit| pressed & (1 << bit) != 0) {
+            let (row, col) = bit_position(bit);
+            self.stats[row][col].record(self.scans);
+        }
+        self.stable = stable;
+        self.run = 0;
+        self.last_scans = self.scans;
+        self.scans = 0;
+        Some(stable)
+    }
+
+    // Records the raw value of every scan from the first one that differs from the debounced
+    // value, until the capture is full
+    #[cfg(feature = "bounce-capture")]
+    fn capture_scan(&mut self, raw: u16) {
+        if self.captured == 0 && raw == self.stable {
+            return;
+        }
+        if let Some(slot) = self.capture.get_mut(self.captured) {
+            *slot = raw;
+            self.captured += 1;
+        }
+    }
+}
+
+// Decodes a quadrature encoder wired to two keys of the matrix, with each key closed while its
+// encoder line is active
+struct Encoder {
+    a: u16,
+    b: u16,
+    phase: Option<usize>,
+    steps: i8,
+}
+
+impl Encoder {
+    // Takes the raw value of a debounced read, adding any steps the encoder moved by since the
+    // last one
+    fn step(&mut self, raw: u16) {
+        let phase = usize::from(raw & self.a != 0) << 1 | usize::from(raw & self.b != 0);
+        if let Some(previous) = self.phase {
+            self.steps = self
+                .steps
+                .saturating_add(QUADRATURE_STEPS[previous << 2 | phase]);
+        }
+        self.phase = Some(phase);
+    }
+
+    // Returns the steps moved since the last call, if any
+    fn take_steps(&mut self) -> Option<i8> {
+        Some(core::mem::replace(&mut self.steps, 0)).filter(|&steps| steps != 0)
+    }
+}
+
+/// How many scans presses of one key took to settle while debouncing, from
+/// [`Keypad::bounce_metrics`]. Every count includes the scans that had to agree, so a key that
+/// never bounced reports the number of debounce samples.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BounceStats {
+    /// Fewest scans any press took, or `0` if the key hasn't been pressed
+    pub min: u8,
+    /// Most scans any press took
+    pub max: u8,
+    /// Scans the most recent press took
+    pub last: u8,
+}
+
+impl BounceStats {
+    fn record(&mut self, scans: u8) {
+        self.min = if self.min == 0 {
+            scans
+        } else {
+            self.min.min(scans)
+        };
+        self.max = self.max.max(scans);
+        self.last = scans;
+    }
+}
+
+/// Stands in for an optional pin that isn't connected. Setting it does nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NoPin;
+
+impl OutputPin for NoPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+/**
+Reads a single key from a keypad, for application code that shouldn't depend on the keypad's
+type or size. For example, a PIN entry screen can be written as:
+
+```ignore
+fn next_digit(keypad: &mut impl KeypadRead, delay: &mut dyn DelayMs<u16>) -> Option<Key> {
+    keypad.read_key(delay).filter(|key| key.to_char().is_ascii_digit())
+}
+```
+*/
+pub trait KeypadRead {
+    /// Scans the keypad and returns the single key that's down, or `None` when no key, or more
+    /// than one key, is down, or when the key's character isn't a [`Key`]
+    fn read_key(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<Key>;
+}
+
+/// Number of rows the keypad can have
+pub const ROWS: usize = 4;
+
+/// Number of columns the keypad can have
+pub const COLS: usize = 3;
+
+/// Characters for each key, indexed by row and then column from the top left
+pub type Keymap = [[char; COLS]; ROWS];
+
+/// The standard telephone layout, with `1`, `2`, `3` along the top row
+pub const DEFAULT_KEYMAP: Keymap = [
+    ['1', '2', '3'],
+    ['4', '5', '6'],
+    ['7', '8', '9'],
+    ['*', '0', '#'],
+];
+
+/// The calculator layout, with `7`, `8`, `9` along the top row
+pub const CALCULATOR_KEYMAP: Keymap = [
+    ['7', '8', '9'],
+    ['4', '5', '6'],
+    ['1', '2', '3'],
+    ['*', '0', '#'],
+];
+
+/// How the columns are left between scans, set with [`Keypad::with_idle_state`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleState {
+    /// Every column is released, which is the default. With open-drain columns they float, which
+    /// leaks the least current.
+    Released,
+    /// Every scanned column is driven, as by [`Keypad::arm_wake`], so pressing any key pulls its
+    /// row low and can wake the device
+    Armed,
+    /// The last column scanned is left driven, which saves writing the pins after each scan.
+    /// Its keys pull their rows low while idle.
+    LastColumn,
+}
+
+/// How each column is selected during a scan, set with [`Keypad::with_scan_strategy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanStrategy {
+    /// The column being read is driven while the others are released, which is the default.
+    /// A key reads as pressed when its row follows its column.
+    DriveOne,
+    /**
+    Every scanned column is driven while the rows are read once, then each column in turn is
+    released while the others stay driven. A key reads as pressed when its row was pulled by
+    the first read but lets go when its column is released. Starting from columns that idle
+    driven, as with [`IdleState::Armed`], each column only needs one pin released and driven
+    again, which can save writes on ports that change pins one at a time.
+
+    Multiple keys fail the other way round from [`DriveOne`](Self::DriveOne). While two keys in
+    one row are down, releasing either column leaves the row pulled through the other, so
+    neither reads as pressed and the row looks empty. Three keys at the corners of a rectangle
+    pull both of their rows through every column, so instead of a ghost key at the fourth
+    corner, the whole scan can read as no keys. Keys get missed rather than invented, which
+    makes this best suited to reading one key at a time.
+
+    This needs columns that can be driven together, so it can't be used with
+    [`DemuxColumns`], where driving more than one column selects none and no keys are read.
+    */
+    ReleaseOne,
+}
+
+/// What a read of a single key does when more than one key is down, set with
+/// [`Keypad::with_multi_key_policy`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MultiKeyPolicy {
+    /// Reads as no key being down, which is the default
+    Reject,
+    /// Reads the key with the lowest bit in the raw value, which is the one in the lowest
+    /// numbered column, and then the lowest numbered row
+    Lowest,
+    /// Makes [`Keypad::try_read_char`] return [`Error::MultipleKeys`]. The other reads treat it
+    /// like [`Reject`](Self::Reject).
+    Error,
+}
+
+/// How a row pin reads while a key in the row is pressed, set with
+/// [`Keypad::with_row_polarity`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    /// The row reads low, as with a pull-up resistor on the row
+    ActiveLow,
+    /// The row reads high, as with a pull-down resistor on the row
+    ActiveHigh,
+}
+
+/// Common keypad layouts, selected with [`Keypad::with_layout`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutPreset {
+    /// `1`, `2`, `3` along the top row, as on a phone. This is the default.
+    Telephone,
+    /// `7`, `8`, `9` along the top row, as on a calculator
+    Calculator,
+}
+
+impl LayoutPreset {
+    /// Returns the keymap for this layout
+    pub const fn keymap(self) -> Keymap {
+        match self {
+            LayoutPreset::Telephone => DEFAULT_KEYMAP,
+            LayoutPreset::Calculator => CALCULATOR_KEYMAP,
+        }
+    }
+}
+
+/**
+Settings for a keypad, applied all at once with [`Keypad::new_with_config`] or
+[`Keypad::with_config`]
+
+This can be defined once as a `const` and shared by many devices:
+
+```
+# use keypad2::KeypadConfig;
+const CONFIG: KeypadConfig = KeypadConfig {
+    debounce_samples: 3,
+    ..KeypadConfig::new()
+};
+```
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeypadConfig {
+    /// How long each column is driven before its rows are read, in ms
+    pub settle_ms: u16,
+    /// How many scans in a row must agree before a read is accepted
+    pub debounce_samples: u8,
+    /// Set when a row reads high while a key in it is pressed, as with pull-down resistors on
+    /// the rows. Keypads with fewer than four rows should then mask the missing rows with
+    /// [`Keypad::with_active_rows`].
+    pub active_high: bool,
+    /// Set when a column is selected by driving it high rather than low
+    pub inverted: bool,
+    /// Which characters the keys report
+    pub layout: LayoutPreset,
+}
+
+impl KeypadConfig {
+    /// Returns the default settings: 1ms settle time, no debouncing, rows that read low when
+    /// pressed, columns selected by driving them low, and the telephone layout
+    pub const fn new() -> Self {
+        Self {
+            settle_ms: 1,
+            debounce_samples: 1,
+            active_high: false,
+            inverted: false,
+            layout: LayoutPreset::Telephone,
+        }
+    }
+}
+
+impl Default for KeypadConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Problems with a custom keymap, reported by [`Keypad::new_with_map`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeymapError {
+    /// This character is on more than one key, so keys with it can't be told apart
+    DuplicateChar(char),
+}
+
+/// Problems detected while reading the keypad
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Every row being read, as set with [`Keypad::with_active_rows`], read as pressed while
+    /// the column with this index was driven, including keys the digits-only filter ignores.
+    /// This usually means the column is shorted to ground, though holding every key in the
+    /// column down looks the same.
+    ShortedColumn(usize),
+    /// More than one key was down, which is only reported with
+    /// [`MultiKeyPolicy::Error`]. This holds the raw value of the scan.
+    MultipleKeys(u16),
+}
+
+/// How far [`Keypad::scan_at`] has got through a scan, returned by [`Keypad::phase`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanPhase {
+    /// No scan is in progress, so the next call starts one
+    Idle,
+    /// The column with this index is driven, and its rows are settling before being read
+    Settling(usize),
+}
+
+/// A change in which key is held down, or a turn of an encoder, returned by [`Keypad::poll`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    /// The key with this character was pressed
+    Pressed {
+        /// Character of the key
+        key: char,
+        /// Time passed to [`Keypad::poll_at`] or [`Keypad::scan_at`] when the press was
+        /// accepted, or `None` when polling without a time
+        time_ms: Option<u32>,
+    },
+    /// The key with this character was released
+    Released {
+        /// Character of the key
+        key: char,
+        /// Time passed to [`Keypad::poll_at`] or [`Keypad::scan_at`] when the release was
+        /// seen, or `None` when polling without a time
+        time_ms: Option<u32>,
+    },
+    /// The encoder set up with [`Keypad::with_encoder`] turned by this many steps, which are
+    /// positive when its `a` line changes ahead of its `b` line. Most encoders step four times
+    /// per detent.
+    Rotate(i8),
+}
+
+impl KeyEvent {
+    /// Returns the character of the key that was pressed or released, or `None` for an encoder
+    /// turning
+    pub fn key(&self) -> Option<char> {
+        match *self {
+            KeyEvent::Pressed { key, .. } | KeyEvent::Released { key, .. } => Some(key),
+            KeyEvent::Rotate(_) => None,
+        }
+    }
+
+    /// Returns the time the event happened, if the keypad was given one. Encoder events don't
+    /// have a time.
+    pub fn time_ms(&self) -> Option<u32> {
+        match *self {
+            KeyEvent::Pressed { time_ms, .. } | KeyEvent::Released { time_ms, .. } => time_ms,
+            KeyEvent::Rotate(_) => None,
+        }
+    }
+
+    // Returns the same event without a time, for events found against the estimated clock
+    fn untimed(self) -> Self {
+        match self {
+            KeyEvent::Pressed { key, .. } => KeyEvent::Pressed { key, time_ms: None },
+            KeyEvent::Released { key, .. } => KeyEvent::Released { key, time_ms: None },
+            KeyEvent::Rotate(steps) => KeyEvent::Rotate(steps),
+        }
+    }
+}
+
+/**
+Everything learned from a single scan of the keypad, returned by [`Keypad::scan_once`]
+
+A snapshot is a copy of what the keypad read at one instant, and doesn't borrow the keypad or
+change as keys are pressed later. It can be kept, or moved into an async block and used across
+await points, while the keypad goes on being scanned.
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanSnapshot {
+    /// Raw value of the scan, with a bit set for each key that was down
+    pub raw: u16,
+    /// Number of keys that were down
+    pub pressed: u8,
+    /// Character of the key that was down, or `None` unless exactly one key was down
+    pub key: Option<char>,
+}
+
+/// What [`Keypad::edit_buffer`] did to the buffer
+#[cfg(feature = "heapless")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditAction {
+    /// This digit was added to the end of the buffer
+    Digit(char),
+    /// `*` was pressed, and the buffer was cleared
+    Cleared,
+    /// `#` was pressed, so the entry is finished. The buffer is left as it is.
+    Entered,
+    /// Nothing changed
+    None,
+}
+
+/// Result of [`Keypad::handle_wake_profiled`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WakeProfile {
+    /// Character of the single key that was down, if any
+    pub key: Option<char>,
+    /// Number of scans the debounced read took before it was accepted
+    pub scans: u8,
+    /// Time spent settling during those scans, in ms. Reading the pins adds a little more.
+    pub settle_ms: u32,
+}
+
+/// Result of [`Keypad::calibrate`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CalibrationReport {
+    /// Raw value read with the configured settle time while the key was held
+    pub raw: u16,
+    /// Total time spent settling during one scan with the configured settle time, in ms
+    pub scan_ms: u16,
+    /// Shortest settle time that still read `raw` on every sample. This is `None` when no key
+    /// was held, or when the reads weren't stable even with the configured settle time.
+    pub min_settle_ms: Option<u16>,
+}
+
+impl<R: RowPins, C: PortColumns> Keypad<R, C> {
+    /// Create a new instance of this structure. The rows and columns are usually [`Rows`] and
+    /// [`Columns`] tuples of pins, but can be any [`RowPins`] and [`PortColumns`]
+    /// implementations. Keypads with fewer rows or columns can pass shorter tuples, and should
+    /// limit scanning to the columns they have with
+    /// [`with_active_columns`](Keypad::with_active_columns).
+    pub fn new(rows: R, columns: C) -> Self {
+        Self {
+            rows,
+            columns,
+            backlight: NoPin,
+            strobe: NoPin,
+            state: State::new(),
+        }
+    }
+
+    /// Create a new instance with all of the settings in `config`
+    pub fn new_with_config(rows: R, columns: C, config: KeypadConfig) -> Self {
+        Self::new(rows, columns).with_config(config)
+    }
+
+    /// Create a new instance that reports the characters in `keymap` instead of the standard
+    /// telephone layout. Fails if any character appears on more than one key.
+    pub fn new_with_map(rows: R, columns: C, keymap: Keymap) -> Result<Self, KeymapError> {
+        if let Some(c) = find_duplicate(&keymap) {
+            return Err(KeymapError::DuplicateChar(c));
+        }
+        let mut keypad = Self::new(rows, columns);
+        keypad.state.set_keymap(keymap);
+        Ok(keypad)
+    }
+}
+
+impl<R0, R1, R2, R3, C0, C1, C2> Keypad<Rows<R0, R1, R2, R3>, Columns<C0, C1, C2>>
+where
+    R0: InputPin,
+    R1: InputPin,
+    R2: InputPin,
+    R3: InputPin,
+    C0: OutputPin,
+    C1: OutputPin,
+    C2: OutputPin,
+{
+    /// Create a new instance from all seven pins in one tuple, the four rows followed by the
+    /// three columns. This is the same as [`new`](Keypad::new) with the pins split into
+    /// [`Rows`] and [`Columns`].
+    pub fn from_tuple(pins: (R0, R1, R2, R3, C0, C1, C2)) -> Self {
+        let (r0, r1, r2, r3, c0, c1, c2) = pins;
+        Self::new((r0, r1, r2, r3), (c0, c1, c2))
+    }
+}
+
+impl<R: RowPins, C: PortColumns, S: OutputPin> Keypad<R, C, NoPin, S> {
+    /// Gives the keypad the pin that controls its backlight, for keypad modules that have one,
+    /// so [`set_backlight`](Keypad::set_backlight) can switch it. Set `active_low` when the
+    /// backlight turns on with the pin driven low, as with a common-anode LED.
+    pub fn with_backlight<B: OutputPin>(
+        self,
+        backlight: B,
+        active_low: bool,
+    ) -> Keypad<R, C, B, S> {
+        let mut keypad = Keypad {
+            rows: self.rows,
+            columns: self.columns,
+            backlight,
+            strobe: self.strobe,
+            state: self.state,
+        };
+        keypad.state.backlight_active_low = active_low;
+        keypad
+    }
+}
+
+impl<R: RowPins, C: PortColumns, B: OutputPin> Keypad<R, C, B, NoPin> {
+    /// Gives the keypad a pin that enables it, such as the enable input of an analog switch
+    /// that isolates the keypad from lines it shares. The pin is asserted at the start of each
+    /// scan and deasserted at the end, and stays asserted while the columns are armed by
+    /// [`arm_wake`](Keypad::arm_wake). Set `active_low` when the keypad is enabled with the pin
+    /// driven low.
+    pub fn with_strobe<S: OutputPin>(self, strobe: S, active_low: bool) -> Keypad<R, C, B, S> {
+        let mut keypad = Keypad {
+            rows: self.rows,
+            columns: self.columns,
+            backlight: self.backlight,
+            strobe,
+            state: self.state,
+        };
+        keypad.state.strobe_active_low = active_low;
+        keypad.set_strobe(false);
+        keypad
+    }
+}
+
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Keypad<R, C, B, S> {
+    /// Borrows the row pins, for example to read a row that doubles as a sense line between
+    /// scans. Leave the pins configured as inputs, as the next scan expects.
+    pub fn rows_mut(&mut self) -> &mut R {
+        &mut self.rows
+    }
+
+    /// Borrows the column pins, for example to drive one that's shared with something else
+    /// between scans. The next scan writes every column pin rather than only the ones it expects
+    /// to change, so the pins don't have to be put back, but they mustn't be changed while a
+    /// [`scan_at`](Self::scan_at) scan is part way through.
+    pub fn columns_mut(&mut self) -> &mut C {
+        self.state.column_pattern = None;
+        &mut self.columns
+    }
+
+    /// Turns the backlight on or off. This does nothing unless a backlight pin was supplied
+    /// with [`with_backlight`](Keypad::with_backlight).
+    pub fn set_backlight(&mut self, on: bool) {
+        set_level(&mut self.backlight, on != self.state.backlight_active_low);
+    }
+
+    /// Applies every setting in `config`, replacing any set earlier with the matching `with_`
+    /// methods, and the keymap with the config's layout
+    pub fn with_config(self, config: KeypadConfig) -> Self {
+        let mut keypad = self
+            .with_settle_ms(config.settle_ms)
+            .with_debounce_samples(config.debounce_samples)
+            .with_layout(config.layout);
+        keypad.state.active_high_rows = if config.active_high { 0b1111 } else { 0 };
+        keypad.state.inverted = config.inverted;
+        keypad
+    }
+
+    /// Releases every column and waits for the settle time before driving each column during a
+    /// scan, so charge left on a long cable by the last column can drain away instead of
+    /// reading as presses in the next. This doubles the time each scan spends settling. Only the
+    /// blocking and async scans can wait, so [`scan_at`](Self::scan_at) and
+    /// [`scan_no_delay`](Self::scan_no_delay) don't give the charge time to drain.
+    pub fn with_charge_flush(mut self, flush: bool) -> Self {
+        self.state.charge_flush = flush;
+        self
+    }
+
+    /// Makes every read ignore the keys that aren't digits, such as `*` and `#`, as though they
+    /// weren't there, for numeric entry fields. Pressing them does nothing, and holding one
+    /// alongside a digit still reads the digit. [`on_scan`](Self::on_scan) still sees them.
+    pub fn with_digits_only_filter(mut self, digits_only: bool) -> Self {
+        self.state.digits_only = digits_only;
+        self
+    }
+
+    /// Sets how the columns are left after each scan, and by [`park`](Self::park). The default
+    /// releases every column.
+    pub fn with_idle_state(mut self, idle_state: IdleState) -> Self {
+        self.state.idle_state = idle_state;
+        self
+    }
+
+    /// Sets how the columns are selected by the blocking and async scans. The default drives one
+    /// column at a time. [`scan_at`](Self::scan_at) and [`check_settle`](Self::check_settle)
+    /// always do.
+    pub fn with_scan_strategy(mut self, strategy: ScanStrategy) -> Self {
+        self.state.scan_strategy = strategy;
+        self
+    }
+
+    /// Sets what reading a single key does when more than one is down, after any tolerated stray
+    /// key is dropped. The default reads it as no key down.
+    pub fn with_multi_key_policy(mut self, policy: MultiKeyPolicy) -> Self {
+        self.state.multi_key_policy = policy;
+        self
+    }
+
+    /// Sets how each row reads while a key in it is pressed, for boards with pull-ups on some
+    /// rows and pull-downs on others. This replaces the config's `active_high`, which sets every
+    /// row the same way.
+    pub fn with_row_polarity(mut self, polarity: [Polarity; ROWS]) -> Self {
+        self.state.active_high_rows = polarity
+            .iter()
+            .enumerate()
+            .filter(|(_, &polarity)| polarity == Polarity::ActiveHigh)
+            .fold(0, |mask, (row, _)| mask | 1 << row);
+        self
+    }
+
+    /// Sets how long each column is driven before its rows are read. The default is 1ms.
+    pub fn with_settle_ms(mut self, settle_ms: u16) -> Self {
+        self.state.settle_ms = settle_ms;
+        self
+    }
+
+    /// Ignores the rows whose bits are clear in `mask`, with bit `n` for row `n`, so keys in
+    /// them never read as pressed. This lets a keypad with fewer rows leave the spare row pins
+    /// unconnected without a floating pin reading as spurious presses. All four are read by
+    /// default.
+    pub fn with_active_rows(mut self, mask: u8) -> Self {
+        self.state.active_rows = mask & ALL_ROWS as u8;
+        self
+    }
+
+    /// Decodes a read with two keys down as the other key when one of them is at one of the
+    /// `stray` `(row, col)` positions, for keypads with a worn contact that sometimes reads as
+    /// pressed alongside the intended key. Reads with more keys down, or where both are stray
+    /// positions, still decode as no key.
+    pub fn with_lenient_decode(mut self, stray: &[(usize, usize)]) -> Self {
+        self.state.stray_mask = stray
+            .iter()
+            .filter(|&&(row, col)| row < 4 && col < 3)
+            .fold(0, |mask, &(row, col)| mask | key_mask(row, col));
+        self
+    }
+
+    /// Returns the row pins that are read, with bit `n` set for row `n`, for example to choose
+    /// which rows to enable interrupts on
+    pub fn row_pins_mask(&self) -> u8 {
+        self.state.active_rows
+    }
+
+    /// Returns the column pins that are scanned, with bit `n` set for column `n`
+    pub fn column_pins_mask(&self) -> u8 {
+        self.state.active_columns
+    }
+
+    /// Returns the row pins, with bit `n` set for row `n`, that the keys with these characters
+    /// pull low when pressed. Only these rows need interrupts to wake on those keys.
+    pub fn rows_for_keys(&self, chars: &[char]) -> u8 {
+        chars
+            .iter()
+            .filter_map(|&c| self.char_to_position(c))
+            .fold(0, |mask, (row, _)| mask | 1 << row)
+            & self.state.active_rows
+    }
+
+    /// Switches the keymap to one of the common layouts
+    pub fn with_layout(mut self, layout: LayoutPreset) -> Self {
+        self.state.set_keymap(layout.keymap());
+        self
+    }
+
+    /// Installs `keymap` in place of the current keymap and returns the one it replaced, so
+    /// modes can switch to a temporary keymap and later restore the previous one. Unlike
+    /// [`new_with_map`](Keypad::new_with_map), this doesn't check for duplicate characters,
+    /// which [`validate_keymap`] can do at compile time.
+    pub fn replace_keymap(&mut self, keymap: Keymap) -> Keymap {
+        let previous = self.state.keymap;
+        self.state.set_keymap(keymap);
+        previous
+    }
+
+    /// Limits scanning to the columns whose bits are set in `mask`, with bit `n` for column
+    /// `n`. Other columns are never driven or waited on, so keys in them never read as pressed.
+    /// This suits keypads with an unused or unpopulated column. All three are scanned by default.
+    pub fn with_active_columns(mut self, mask: u8) -> Self {
+        self.state.active_columns = mask & COLUMNS_RELEASED;
+        self
+    }
+
+    /// Drives the columns in the order given, for example `[2, 1, 0]` to scan them in reverse,
+    /// rather than from column 0 up. Keys still read at their own positions. `order` should list
+    /// each column once, since a column that's left out isn't scanned, and numbers past the last
+    /// column are skipped.
+    pub fn with_scan_order(mut self, order: [u8; COLS]) -> Self {
+        self.state.scan_order = order;
+        self
+    }
+
+    /// Debounces reads by scanning until `samples` scans in a row agree, which takes at least
+    /// `samples` times as long as a single scan. A scan that won't settle within a few times
+    /// that many scans reads as no keys. The default of `1` doesn't debounce.
+    pub fn with_debounce_samples(mut self, samples: u8) -> Self {
+        self.state.debouncer.samples = samples.max(1);
+        self
+    }
+
+    /// Makes [`poll`](Self::poll) hold back a key's press until the key has been held for
+    /// `hold_ms`, and discard presses released sooner than that, so a brief tap is ignored
+    /// entirely. A key whose press was discarded doesn't report its release either. `0`, the
+    /// default, reports presses straight away.
+    pub fn with_confirm_hold_ms(mut self, hold_ms: u32) -> Self {
+        self.state.confirm_hold_ms = hold_ms;
+        self
+    }
+
+    /**
+    Treats the keys with characters `a` and `b` as the two lines of a rotary encoder, for
+    modules that wire an encoder into the matrix. Turning it makes [`poll`](Self::poll)
+    report [`KeyEvent::Rotate`] instead of presses of those keys, which every other read
+    ignores too, including [`poll_matrix`](Self::poll_matrix) and the keys counted by a
+    [`ScanSnapshot`]. Only the raw values, such as the snapshot's `raw` and the result of
+    [`scan_no_delay`](Self::scan_no_delay), and [`on_scan`](Self::on_scan) still show them.
+    Does nothing if either character isn't in the keymap.
+
+    Each debounced read counts as one sample of the encoder, so poll quickly enough for the
+    lines to be seen in every position as it turns.
+    */
+    pub fn with_encoder(mut self, a: char, b: char) -> Self {
+        if let (Some(a), Some(b)) = (self.char_to_position(a), self.char_to_position(b)) {
+            self.state.encoder = Some(Encoder {
+                a: key_mask(a.0, a.1),
+                b: key_mask(b.0, b.1),
+                phase: None,
+                steps: 0,
+            });
+        }
+        self
+    }
+
+    /// Returns the character this keypad's keymap has at `row` and `col`, counting from zero at
+    /// the top left, or `None` if the position is off the keypad
+    pub fn position_to_char(&self, row: usize, col: usize) -> Option<char> {
+        self.state.keymap.get(row)?.get(col).copied()
+    }
+
+    /// Returns the `(row, col)` of the key with this character in this keypad's keymap, or
+    /// `None` if no key has that character
+    pub fn char_to_position(&self, c: char) -> Option<(usize, usize)> {
+        keymap_position(&self.state.keymap, c)
+    }
+
+    /**
+    Reads a character from the keypad. This method returns even if no keys are pressed.
+    It will return:
+
+    * `'0'` through `'9'`
+    * `'*'`
+    * `'#'`
+    * `' '` if no keys, or more than one key, are pressed.
+
+    With a custom keymap, it returns the keymap's character for the pressed key instead. While
+    a macro set with [`with_macro`](Self::with_macro) is being played back, this returns its
+    characters instead, without scanning.
+    */
+    pub fn read_char(&mut self, delay: &mut dyn DelayMs<u16>) -> char {
+        if let Some((&next, rest)) = self.state.pending.split_first() {
+            self.state.pending = rest;
+            return next;
+        }
+
+        let key = self.read_decoded(delay).unwrap_or(' ');
+        let expansion = self
+            .state
+            .macros
+            .iter()
+            .flatten()
+            .find(|&&(trigger, _)| trigger == key);
+        match expansion.map(|(_, expansion)| expansion.split_first()) {
+            Some(Some((&first, rest))) => {
+                self.state.pending = rest;
+                first
+            }
+            Some(None) => ' ',
+            None => key,
+        }
+    }
+
+    /**
+    Makes [`read_char`](Self::read_char) play back `expansion` when it reads the key with
+    character `trigger`, returning the first character of `expansion` in place of `trigger`
+    and the rest from the calls that follow, one each, before it scans the keypad again. Each
+    read that finds the trigger down starts the expansion over, so wait for the key to be
+    released as with any other key.
+
+    Up to four macros can be set, and setting one for the same trigger again replaces it. Any
+    more are ignored. An empty `expansion` makes the trigger read as no key.
+    */
+    pub fn with_macro(mut self, trigger: char, expansion: &'static [char]) -> Self {
+        let slot = self
+            .state
+            .macros
+            .iter()
+            .position(|slot| matches!(slot, Some((key, _)) if *key == trigger))
+            .or_else(|| self.state.macros.iter().position(Option::is_none));
+        if let Some(slot) = slot {
+            self.state.macros[slot] = Some((trigger, expansion));
+        }
+        self
+    }
+
+    /// Reads the pressed key as its ASCII byte, such as `b'5'`, `b'*'` or `b'#'`, for sending
+    /// straight to a byte stream. Returns `None` when no single key is down, or when a custom
+    /// keymap gives the key a character that isn't ASCII.
+    pub fn read_byte(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<u8> {
+        self.read_decoded(delay)
+            .filter(char::is_ascii)
+            .map(|c| c as u8)
+    }
+
+    /// Reads the pressed key as a number, with the digits as `0` through `9`, `*` as `10` and
+    /// `#` as `11`, the same codes as [`scancode`]. The numbers are looked up by the key's
+    /// position, without going through its character, which keeps this small on targets where
+    /// code size matters. Returns `None` when no single key is down, or when a custom keymap
+    /// gives the key some other character.
+    pub fn read_u8(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<u8> {
+        let raw = self.read(delay);
+        let raw = self.single_key(self.strip_stray(raw));
+        if raw.count_ones() != 1 {
+            self.report_invalid(raw);
+            return None;
+        }
+        let (row, col) = bit_position(raw.trailing_zeros() as usize);
+        let code = self.state.codes.get(row)?.get(col)?;
+        Some(*code).filter(|&code| code != NO_CODE)
+    }
+
+    /// Same as [`read_char`](Self::read_char), but reports wiring faults instead of
+    /// returning the garbage they read as. Returns `Ok(None)` when no single key is down.
+    pub fn try_read_char(&mut self, delay: &mut dyn DelayMs<u16>) -> Result<Option<char>, Error> {
+        let raw = self.read(delay);
+        // Check before the digits-only filter clears some of a shorted column's keys, against
+        // the rows that are read at all
+        let unfiltered = self.state.unfiltered_raw;
+        let rows = u16::from(self.state.active_rows);
+        let shorted = |column: &usize| rows != 0 && (unfiltered >> (column * 4)) & rows == rows;
+        if let Some(column) = (0..3).find(shorted) {
+            return Err(Error::ShortedColumn(column));
+        }
+        if self.state.multi_key_policy == MultiKeyPolicy::Error
+            && self.strip_stray(raw).count_ones() > 1
+        {
+            return Err(Error::MultipleKeys(raw));
+        }
+        Ok(self.decode(raw))
+    }
+
+    /**
+    Runs each column's drive, settle and read inside a critical section, so another task
+    sharing an I/O expander with the keypad can't change the pins part way through. `cs` must
+    call the function it's given exactly once, with whatever protection the application needs,
+    for example:
+
+    ```ignore
+    fn critical_section(f: &mut dyn FnMut()) {
+        cortex_m::interrupt::free(|_| f())
+    }
+
+    let keypad = Keypad::new(rows, columns).with_critical_section(critical_section);
+    ```
+
+    Since the settle time is spent inside the critical section, keep it short. This covers the
+    blocking reads; [`scan_at`](Self::scan_at) and the async scans spread a column's drive and
+    read over separate steps, so they can't hold a critical section across them. Pins on the
+    microcontroller itself don't need this.
+    */
+    pub fn with_critical_section(mut self, cs: fn(&mut dyn FnMut())) -> Self {
+        self.state.critical_section = Some(cs);
+        self
+    }
+
+    /**
+    Makes [`poll`](Self::poll) report a different character for some keys when they're held
+    for at least `hold_ms`, as a phone reports `+` for a held `0`. Each pair in `map` is a key's
+    character and the character to report when it's held:
+
+    ```ignore
+    let keypad = Keypad::new(rows, columns).with_longpress_map(800, &[('0', '+'), ('1', 'V')]);
+    ```
+
+    The press of a key in `map` isn't reported until it's been held for `hold_ms`, when its
+    long-press character is pressed, or until it's released sooner, when the key's own press
+    and release are reported by consecutive calls. The release of a long press reports the
+    long-press character too.
+    */
+    pub fn with_longpress_map(mut self, hold_ms: u32, map: &'static [(char, char)]) -> Self {
+        self.state.longpress_ms = hold_ms;
+        self.state.longpress_map = map;
+        self
+    }
+
+    /// Makes [`read_char`](Self::read_char), [`read_byte`](Self::read_byte) and
+    /// [`KeypadRead::read_key`] read the keypad again, up to `retries` more times, when a read
+    /// has more than one key down, to ride out brief glitches. Each retry is a full debounced
+    /// read. A read with no keys down isn't retried. The default of `0` never retries.
+    pub fn with_retries(mut self, retries: u8) -> Self {
+        self.state.retries = retries;
+        self
+    }
+
+    /// Makes [`poll`](Self::poll) lock onto the first key pressed, ignoring every other key
+    /// until that key is released, rather than treating other keys pressed alongside it as an
+    /// invalid read. A key still held when the locked key is released is then reported as
+    /// pressed.
+    pub fn with_lock_on_first(mut self, lock: bool) -> Self {
+        self.state.lock_on_first = lock;
+        self
+    }
+
+    /// Makes [`poll`](Self::poll) ignore a press of the same key that comes within `gap_ms` of
+    /// that key's last reported release, which filters out a finger bouncing on the key. The
+    /// ignored press doesn't report its release either. `0`, the default, accepts every press.
+    pub fn with_min_repress_ms(mut self, gap_ms: u32) -> Self {
+        self.state.min_repress_ms = gap_ms;
+        self
+    }
+
+    /**
+    Scans the keypad and reports when a key is pressed or released. Call this regularly, as
+    each call returns at most one event. The held-key state is kept between calls, but the
+    delay isn't, so each call can be given any delay.
+
+    Moving straight from one key to another reports the first key's release, and the next call
+    reports the new key's press. Scans with more than one key down are ignored, unless
+    [`with_multi_key_policy`](Self::with_multi_key_policy) picks one of them.
+
+    Options that depend on how long a key is held measure time by adding up the settle time of
+    each scan, which is only accurate when this is called in a tight loop. Use
+    [`poll_at`](Self::poll_at) to supply the time instead. Events from this have no `time_ms`.
+    */
+    pub fn poll(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<KeyEvent> {
+        if self.state.suspended {
+            return None;
+        }
+        let raw = self.read(delay);
+        let now_ms = self.state.clock_ms.wrapping_add(self.read_ms());
+        self.state.clock_ms = now_ms;
+        self.update_held(raw, now_ms).map(KeyEvent::untimed)
+    }
+
+    /// Same as [`poll`](Self::poll), but measures how long keys are held with `now_ms`, the
+    /// current time in milliseconds from any clock that counts up and wraps around. Events
+    /// carry `now_ms` as their `time_ms`.
+    pub fn poll_at(&mut self, delay: &mut dyn DelayMs<u16>, now_ms: u32) -> Option<KeyEvent> {
+        self.state.clock_ms = now_ms;
+        if self.state.suspended {
+            return None;
+        }
+        let raw = self.read(delay);
+        self.update_held(raw, now_ms)
+    }
+
+    /**
+    Scans the keypad without blocking, reporting the same events as
+    [`poll_at`](Self::poll_at). Each call does one step of the scan: it reads the column that
+    has finished settling and drives the next one, then returns straight away. An event can
+    only be returned by the call that completes a scan.
+
+    Call this often, with `now_ms` from a clock that counts up in milliseconds and wraps
+    around. Avoid the blocking reads while a scan is part way through, as they drive the
+    columns too. With [`with_debounce_samples`](Self::with_debounce_samples), each complete
+    scan is one sample, so keys only change once enough complete scans agree.
+    */
+    pub fn scan_at(&mut self, now_ms: u32) -> Option<KeyEvent> {
+        if self.state.suspended {
+            return None;
+        }
+        let next_step = match self.state.phase {
+            ScanPhase::Idle => {
+                self.state.phase_raw = 0;
+                self.set_strobe(true);
+                0
+            }
+            ScanPhase::Settling(column) => {
+                let settled = now_ms.wrapping_sub(self.state.phase_since_ms)
+                    >= u32::from(self.state.settle_ms);
+                if !settled {
+                    return None;
+                }
+                self.state.phase_raw |= self.read_column() << (column * 4);
+                self.state.phase_step + 1
+            }
+        };
+
+        let order = self.state.scan_order;
+        let next = (next_step..COLS).find(|&step| self.select_column(usize::from(order[step])));
+        if let Some(step) = next {
+            self.state.phase = ScanPhase::Settling(usize::from(order[step]));
+            self.state.phase_step = step;
+            self.state.phase_since_ms = now_ms;
+            return None;
+        }
+
+        self.park();
+        let raw = self.complete_scan(self.state.phase_raw);
+        self.state.phase = ScanPhase::Idle;
+        self.state.clock_ms = now_ms;
+        let raw = self.state.debouncer.step(raw)?;
+        let raw = self.remember(raw);
+        self.update_held(raw, now_ms)
+    }
+
+    /**
+    Takes one debounce sample without waiting, for driving debouncing from a hardware timer.
+    Call it from the timer's interrupt. Each call does a single scan, the same as
+    [`scan_no_delay`](Self::scan_no_delay), and once the number of samples set with
+    [`with_debounce_samples`](Self::with_debounce_samples) agree, this reports the same events
+    as [`poll`](Self::poll), without a `time_ms`.
+
+    The columns are driven and read back to back within the call, so the tick only spaces out
+    the samples, and doesn't give the rows any time to settle. Like `scan_no_delay`, this is
+    only reliable when the rows settle faster than the pins can be read. Otherwise, call
+    [`scan_at`](Self::scan_at) from the timer instead, which reads one column per call.
+
+    Options that depend on how long a key is held count each sample as one scan of the settle
+    time, as `poll` does, so durations given to them are only right when the tick matches it.
+    */
+    pub fn feed_sample(&mut self) -> Option<KeyEvent> {
+        if self.state.suspended {
+            return None;
+        }
+        let now_ms = self.state.clock_ms.wrapping_add(self.scan_ms());
+        self.state.clock_ms = now_ms;
+        let raw = self.scan(None, 0);
+        let stable = self.state.debouncer.step(raw)?;
+        let raw = self.remember(stable);
+        self.update_held(raw, now_ms).map(KeyEvent::untimed)
+    }
+
+    /// Returns the character of the single key that was down at the end of the last read, or
+    /// `None` if no single key was down, without scanning the keypad. Every read of a key
+    /// updates this, including [`poll`](Self::poll) and [`scan_at`](Self::scan_at), so one task
+    /// can poll while others check the current key. The raw scans, such as
+    /// [`scan_no_delay`](Self::scan_no_delay), don't.
+    pub fn last_key(&self) -> Option<char> {
+        self.state.last_key
+    }
+
+    /// Returns how far [`scan_at`](Self::scan_at) has got through the current scan, so a
+    /// cooperative scheduler can decide when to run other work
+    pub fn phase(&self) -> ScanPhase {
+        self.state.phase
+    }
+
+    // Advances the held-key and encoder state with the raw value of a new scan, returning the
+    // event it causes, if any. Encoder steps wait for a scan without a key event.
+    fn update_held(&mut self, raw: u16, now_ms: u32) -> Option<KeyEvent> {
+        if let Some(encoder) = self.state.encoder.as_mut() {
+            encoder.step(raw);
+        }
+        self.update_keys(raw, now_ms).or_else(|| {
+            self.state
+                .encoder
+                .as_mut()?
+                .take_steps()
+                .map(KeyEvent::Rotate)
+        })
+    }
+
+    // Advances the held-key state with the raw value of a new scan, returning the event it
+    // causes, if any
+    fn update_keys(&mut self, raw: u16, now_ms: u32) -> Option<KeyEvent> {
+        if let Some(event) = self.state.queued.take() {
+            return Some(event);
+        }
+        let mut raw = self.strip_stray(raw);
+        if self.state.lock_on_first {
+            if let Some(held) = self.state.held {
+                raw &= self.keys_mask(&[held]);
+            }
+        }
+        let raw = self.single_key(raw);
+        if raw.count_ones() > 1 {
+            self.report_invalid(raw);
+            return None;
+        }
+
+        match (self.state.held, self.decode(raw)) {
+            (None, Some(key)) => {
+                self.state.held = Some(key);
+                self.state.held_since_ms = now_ms;
+                self.state.press_suppressed = match self.state.last_release {
+                    Some((released, at_ms)) => {
+                        released == key && now_ms.wrapping_sub(at_ms) < self.state.min_repress_ms
+                    }
+                    None => false,
+                };
+                self.state.reported_as = key;
+                self.state.longpress_pending =
+                    !self.state.press_suppressed && self.longpress_char(key).is_some();
+                self.state.press_reported = !self.state.press_suppressed
+                    && !self.state.longpress_pending
+                    && self.state.confirm_hold_ms == 0;
+                if self.state.press_reported {
+                    Some(KeyEvent::Pressed {
+                        key,
+                        time_ms: Some(now_ms),
+                    })
+                } else {
+                    None
+                }
+            }
+            (Some(held), current) if current != Some(held) => {
+                self.state.held = None;
+                let held_ms = now_ms.wrapping_sub(self.state.held_since_ms);
+                let tapped = core::mem::replace(&mut self.state.longpress_pending, false)
+                    && held_ms >= self.state.confirm_hold_ms;
+                if self.state.press_reported {
+                    self.state.last_release = Some((held, now_ms));
+                    Some(KeyEvent::Released {
+                        key: self.state.reported_as,
+                        time_ms: Some(now_ms),
+                    })
+                } else if tapped {
+                    self.state.last_release = Some((held, now_ms));
+                    self.state.queued = Some(KeyEvent::Released {
+                        key: held,
+                        time_ms: Some(now_ms),
+                    });
+                    Some(KeyEvent::Pressed {
+                        key: held,
+                        time_ms: Some(now_ms),
+                    })
+                } else {
+                    None
+                }
+            }
+            (Some(held), _) if self.state.longpress_pending => {
+                let substitute = self.longpress_char(held)?;
+                if now_ms.wrapping_sub(self.state.held_since_ms) >= self.state.longpress_ms {
+                    self.state.longpress_pending = false;
+                    self.state.press_reported = true;
+                    self.state.reported_as = substitute;
+                    Some(KeyEvent::Pressed {
+                        key: substitute,
+                        time_ms: Some(now_ms),
+                    })
+                } else {
+                    None
+                }
+            }
+            (Some(held), _) if !self.state.press_reported && !self.state.press_suppressed => {
+                if now_ms.wrapping_sub(self.state.held_since_ms) >= self.state.confirm_hold_ms {
+                    self.state.press_reported = true;
+                    Some(KeyEvent::Pressed {
+                        key: held,
+                        time_ms: Some(now_ms),
+                    })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Forgets the held key, returning the release of a key that was reported as pressed, or
+    // the release that was already queued for the next poll
+    fn release_held(&mut self) -> Option<KeyEvent> {
+        if let Some(event) = self.state.queued.take() {
+            return Some(event.untimed());
+        }
+        let held = self.state.held.take()?;
+        self.state.longpress_pending = false;
+        if !self.state.press_reported {
+            return None;
+        }
+        self.state.last_release = Some((held, self.state.clock_ms));
+        Some(KeyEvent::Released {
+            key: self.state.reported_as,
+            time_ms: None,
+        })
+    }
+
+    // Returns the character a long press of this key reports instead, if it has one
+    fn longpress_char(&self, key: char) -> Option<char> {
+        self.state
+            .longpress_map
+            .iter()
+            .find(|&&(from, _)| from == key)
+            .map(|&(_, to)| to)
+    }
 
-/// Defines a type that makes it easier to supply the four pins required for rows in the keypad.
-/// These pins need to support the `embedded_hal::digital::v2::InputPin` trait
-pub type Rows<R0, R1, R2, R3> = (R0, R1, R2, R3);
+    /**
+    Scans the keypad and returns a key's character once it's released, rather than when it's
+    pressed. Call this regularly, as it tracks the key between calls.
 
-/// Defines a type that makes it easier to supply the four pins required for rows in the keypad
-/// These pins need to support the `embedded_hal::digital::v2::OutputPin` trait
-pub type Columns<C0, C1, C2> = (C0, C1, C2);
+    Nothing is returned if the finger slides onto a different key before letting go, or if
+    several keys were down at once, which lets the user cancel by sliding off a key. This
+    keeps its own record of the held key, separate from [`poll`](Self::poll).
+    */
+    pub fn read_on_release(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<char> {
+        let raw = self.read(delay);
+        if self.without_encoder(raw) == 0 {
+            let released = self.state.release_candidate.take();
+            let cancelled = core::mem::replace(&mut self.state.release_cancelled, false);
+            return if cancelled { None } else { released };
+        }
 
-/// Manages the pins and the logic for scanning a keypad
-pub struct Keypad<
-    R0: InputPin,
-    R1: InputPin,
-    R2: InputPin,
-    R3: InputPin,
-    C0: OutputPin,
-    C1: OutputPin,
-    C2: OutputPin,
-> {
-    rows: Rows<R0, R1, R2, R3>,
-    columns: Columns<C0, C1, C2>,
-}
-
-impl<
-        R0: InputPin,
-        R1: InputPin,
-        R2: InputPin,
-        R3: InputPin,
-        C0: OutputPin,
-        C1: OutputPin,
-        C2: OutputPin,
-    > Keypad<R0, R1, R2, R3, C0, C1, C2>
-{
-    /// Create a new instance of this structure
-    pub fn new(rows: Rows<R0, R1, R2, R3>, columns: Columns<C0, C1, C2>) -> Self {
-        Self { rows, columns }
+        let current = self.decode(raw);
+        match self.state.release_candidate {
+            None => {
+                self.state.release_candidate = current;
+                self.state.release_cancelled |= current.is_none();
+            }
+            Some(_) if current != self.state.release_candidate => {
+                self.state.release_cancelled = true;
+            }
+            Some(_) => {}
+        }
+        None
+    }
+
+    /// Sets the function [`run_once`](Self::run_once) calls with a key's character when it's
+    /// pressed
+    pub fn on_press(&mut self, f: fn(char)) {
+        self.state.on_press = Some(f);
+    }
+
+    /// Sets the function [`run_once`](Self::run_once) calls with a key's character when it's
+    /// released
+    pub fn on_release(&mut self, f: fn(char)) {
+        self.state.on_release = Some(f);
+    }
+
+    /// Sets a function to call with the raw value whenever a read has keys down but can't be
+    /// decoded as a single key, for example to beep or log a flaky read. It's called by every
+    /// method that decodes a key.
+    pub fn on_invalid(&mut self, f: fn(u16)) {
+        self.state.on_invalid = Some(f);
+    }
+
+    /// Sets a function to call after every scan with the state of each key, indexed by row and
+    /// then column like the keymap, for example to stream the keypad to a viewer on a host
+    /// while developing. A debounced read calls it for each of its scans. Nothing extra is done
+    /// while no function is set.
+    pub fn on_scan(&mut self, f: fn(&[[bool; COLS]; ROWS])) {
+        self.state.on_scan = Some(f);
+    }
+
+    /// Polls the keypad once and calls the [`on_press`](Self::on_press) or
+    /// [`on_release`](Self::on_release) function for any event
+    pub fn run_once(&mut self, delay: &mut dyn DelayMs<u16>) {
+        match self.poll(delay) {
+            Some(KeyEvent::Pressed { key, .. }) => {
+                if let Some(f) = self.state.on_press {
+                    f(key);
+                }
+            }
+            Some(KeyEvent::Released { key, .. }) => {
+                if let Some(f) = self.state.on_release {
+                    f(key);
+                }
+            }
+            Some(KeyEvent::Rotate(_)) | None => {}
+        }
+    }
+
+    /// Scans the keypad and returns the character of the single key that's down, ignoring any of
+    /// the keys in `ignore`. This lets a key held as a modifier, such as `*`, stay down while
+    /// other keys are read.
+    pub fn read_ignoring(&mut self, delay: &mut dyn DelayMs<u16>, ignore: &[char]) -> Option<char> {
+        let raw = self.read(delay) & !self.keys_mask(ignore);
+        self.decode(raw)
     }
 
     /**
-    Reads a character from the keypad. This method returns even if no keys are pressed.
-    It will return:
-    
-    * `'0'` through `'9'`
-    * `'*'`
-    * `'#'`
-    * `' '` if no keys are pressed.
+    Polls the keypad and applies any key pressed to `buf`, as for the display of a calculator
+    or an entry field. Digits are added to the end of `buf`, `*` clears it, and `#` enters it,
+    leaving it for the caller to use and clear. Call this regularly, as it uses
+    [`poll`](Self::poll) and shares its state.
+
+    A digit that doesn't fit in `buf`, and any other key, does nothing.
     */
-    pub fn read_char(&mut self, delay: &mut dyn DelayMs<u16>) -> char {
+    #[cfg(feature = "heapless")]
+    pub fn edit_buffer<const N: usize>(
+        &mut self,
+        delay: &mut dyn DelayMs<u16>,
+        buf: &mut heapless::String<N>,
+    ) -> EditAction {
+        match self.poll(delay) {
+            Some(KeyEvent::Pressed { key: '*', .. }) => {
+                buf.clear();
+                EditAction::Cleared
+            }
+            Some(KeyEvent::Pressed { key: '#', .. }) => EditAction::Entered,
+            Some(KeyEvent::Pressed { key, .. })
+                if key.is_ascii_digit() && buf.push(key).is_ok() =>
+            {
+                EditAction::Digit(key)
+            }
+            _ => EditAction::None,
+        }
+    }
+
+    /// Scans the keypad until a single key other than `except` is down, and returns its
+    /// character. This blocks for as long as no key, or only `except`, is held, so it suits
+    /// prompts such as "press a different key". While the keypad is suspended it never returns.
+    pub fn wait_for_key_other_than(&mut self, delay: &mut dyn DelayMs<u16>, except: char) -> char {
+        loop {
+            let raw = self.read(delay);
+            match self.decode(raw) {
+                Some(key) if key != except => return key,
+                _ => {}
+            }
+        }
+    }
+
+    /// Writes the characters of every key down in `raw`, as from
+    /// [`scan_no_delay`](Self::scan_no_delay) or a [`ScanSnapshot`], into `buf` joined by
+    /// `+`, such as `"1+4+#"`, for logging chords. `buf` is cleared first, and left empty when
+    /// no keys are down. Keys are listed a column at a time, from the top of each column. Fails
+    /// if `buf` is too short, leaving as much as fit.
+    #[cfg(feature = "heapless")]
+    pub fn combo_string<const N: usize>(
+        &self,
+        raw: u16,
+        buf: &mut heapless::String<N>,
+    ) -> core::fmt::Result {
+        use core::fmt::Write;
+
+        buf.clear();
+        let raw = self.without_encoder(raw);
+        for mask in (0..12).map(|bit| 1 << bit).filter(|&mask| raw & mask != 0) {
+            if !buf.is_empty() {
+                buf.write_char('+')?;
+            }
+            buf.write_char(self.get_char(mask).unwrap_or(' '))?;
+        }
+        Ok(())
+    }
+
+    /// Scans the keypad and writes every key that's down into `out`, returning how many were
+    /// written. Keys are listed a column at a time, from the top of each column, and any beyond
+    /// the length of `out` are left out. With the `heapless` feature,
+    /// `read_keys` returns them in a vector instead.
+    pub fn read_keys_into(&mut self, delay: &mut dyn DelayMs<u16>, out: &mut [Key]) -> usize {
         let raw = self.read(delay);
-        if raw != 0 {
-            self.get_char(raw)
+        let mut count = 0;
+        for (slot, key) in out.iter_mut().zip(self.keys_in(raw)) {
+            *slot = key;
+            count += 1;
+        }
+        count
+    }
+
+    /// Same as [`read_keys_into`](Self::read_keys_into), but returns the keys in a vector with
+    /// room for every key on the keypad
+    #[cfg(feature = "heapless")]
+    pub fn read_keys(&mut self, delay: &mut dyn DelayMs<u16>) -> heapless::Vec<Key, 12> {
+        let raw = self.read(delay);
+        self.keys_in(raw).collect()
+    }
+
+    // Returns the key for each bit set in a raw value, skipping keys whose characters aren't
+    // a Key
+    fn keys_in(&self, raw_value: u16) -> impl Iterator<Item = Key> + '_ {
+        let raw_value = self.without_encoder(raw_value);
+        (0..12)
+            .map(|bit| 1 << bit)
+            .filter(move |&mask| raw_value & mask != 0)
+            .filter_map(move |mask| self.get_char(mask).and_then(Key::from_char))
+    }
+
+    /// Drives every scanned column low so that pressing any key pulls its row low. Use this
+    /// before sleeping with interrupts enabled on the row pins, to wake on a key press.
+    pub fn arm_wake(&mut self) {
+        self.set_strobe(true);
+        self.drive_columns(COLUMNS_RELEASED & !self.state.active_columns);
+    }
+
+    /// Releases the columns driven by [`arm_wake`](Self::arm_wake), ready for normal scanning.
+    /// Every read does this anyway, unless the idle state is [`IdleState::Armed`], so this is
+    /// usually only needed to stop waking on key presses.
+    pub fn disarm_wake(&mut self) {
+        self.drive_columns(COLUMNS_RELEASED);
+        self.set_strobe(false);
+    }
+
+    /// Leaves the columns in the state set with [`with_idle_state`](Self::with_idle_state), and
+    /// disables the keypad with its strobe pin unless the columns are left armed. Every scan
+    /// does this when it's over.
+    pub fn park(&mut self) {
+        match self.state.idle_state {
+            IdleState::Released => {
+                self.drive_columns(COLUMNS_RELEASED);
+                self.set_strobe(false);
+            }
+            IdleState::Armed => self.arm_wake(),
+            IdleState::LastColumn => self.set_strobe(false),
+        }
+    }
+
+    /// Reads the rows with every scanned column driven, as [`arm_wake`](Self::arm_wake) leaves
+    /// them, returning a bit set for each row with a key down, with bit `n` for row `n`. After a
+    /// wake, a non-zero result confirms a real press, while zero means the wake was a glitch and
+    /// the device can go back to sleep. The columns are left driven.
+    pub fn read_rows_all_driven(&mut self) -> u8 {
+        self.arm_wake();
+        self.read_column() as u8
+    }
+
+    /// Returns the row with a key down while every scanned column is driven, for designs with a
+    /// separate interrupt for each row that want to know which one woke them. With keys down in
+    /// several rows, the lowest numbered is returned. `None` means no key is down, so the wake
+    /// was a glitch. The columns are left driven.
+    pub fn woke_by_row(&mut self) -> Option<usize> {
+        let rows = self.read_rows_all_driven();
+        if rows == 0 {
+            None
+        } else {
+            Some(rows.trailing_zeros() as usize)
+        }
+    }
+
+    /**
+    Handles waking from a row interrupt after [`arm_wake`](Self::arm_wake). This releases the
+    columns, performs a debounced read, and arms the columns again before returning the
+    character of the single key that's down, if any.
+
+    With edge-triggered interrupts, a key that's still held when this returns won't wake the
+    device again until it's released and pressed.
+    */
+    pub fn handle_wake(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<char> {
+        self.disarm_wake();
+        let raw = self.read(delay);
+        self.arm_wake();
+        self.decode(raw)
+    }
+
+    /**
+    Stops the keypad from touching its pins or waiting on the delay, for example around
+    timing-sensitive code. While suspended, reads return as if no key is down and
+    [`poll`](Self::poll) and [`scan_at`](Self::scan_at) return `None`, all straight away. The
+    wake and diagnostic methods that drive the pins directly, such as
+    [`arm_wake`](Self::arm_wake) and [`check_settle`](Self::check_settle), aren't affected.
+
+    The pins are left as they are, and any scan [`scan_at`](Self::scan_at) had started is
+    abandoned.
+
+    A key that [`poll`](Self::poll) reported as pressed is released, and the `Released` event
+    for it is returned, without a `time_ms`, so a key held as this is called doesn't stay down
+    until after [`resume`](Self::resume). A key still down then is pressed again. A key that
+    [`read_on_release`](Self::read_on_release) was waiting on is forgotten. The keys held in
+    the separate record of [`poll_matrix`](Self::poll_matrix) are released too, and its next
+    call reports them.
+    */
+    pub fn suspend(&mut self) -> Option<KeyEvent> {
+        self.state.suspended = true;
+        self.state.phase = ScanPhase::Idle;
+        self.state.release_candidate = None;
+        self.state.cells.release_all();
+        self.release_held()
+    }
+
+    /// Undoes [`suspend`](Self::suspend), releasing every column ready for the next scan
+    pub fn resume(&mut self) {
+        self.state.suspended = false;
+        self.park();
+    }
+
+    /// Returns true between calls to [`suspend`](Self::suspend) and [`resume`](Self::resume)
+    pub fn is_suspended(&self) -> bool {
+        self.state.suspended
+    }
+
+    /// Same as [`handle_wake`](Self::handle_wake), but also reports how long the debounced read
+    /// took to confirm the key, for tuning debouncing against wake latency
+    pub fn handle_wake_profiled(&mut self, delay: &mut dyn DelayMs<u16>) -> WakeProfile {
+        let key = self.handle_wake(delay);
+        let scans = if self.state.suspended {
+            0
         } else {
-            ' '
+            self.state.debouncer.last_scans
+        };
+        WakeProfile {
+            key,
+            scans,
+            settle_ms: self.scan_ms() * u32::from(scans),
+        }
+    }
+
+    /// Returns how many scans presses of each key took to settle while debouncing, indexed by
+    /// row and then column like a [`Keypad`]'s keymap. These are updated by every debounced read,
+    /// which is everything except [`scan_no_delay`](Self::scan_no_delay) and
+    /// [`scan_at`](Self::scan_at), and are useful for spotting worn or marginal keys.
+    pub fn bounce_metrics(&self) -> &[[BounceStats; 3]; 4] {
+        &self.state.debouncer.stats
+    }
+
+    /**
+    Copies the raw value of each scan the debounced reads made during the first presses after
+    power-on into `buf`, oldest first, and returns how many were copied. Some keypads bounce
+    far more on their first presses, which the running totals of
+    [`bounce_metrics`](Self::bounce_metrics) hide.
+
+    Recording starts with the first scan that sees a key change, and keeps every scan after it
+    until 64 have been recorded, then stops. This needs the `bounce-capture` feature, so the
+    recording costs nothing when it's not wanted.
+    */
+    #[cfg(feature = "bounce-capture")]
+    pub fn capture_bounce(&self, buf: &mut [u16]) -> usize {
+        let debouncer = &self.state.debouncer;
+        let captured = &debouncer.capture[..debouncer.captured];
+        let count = captured.len().min(buf.len());
+        buf[..count].copy_from_slice(&captured[..count]);
+        count
+    }
+
+    /// Clears the statistics returned by [`bounce_metrics`](Self::bounce_metrics), and starts
+    /// the recording for `capture_bounce` again when it's enabled
+    pub fn reset_bounce_metrics(&mut self) {
+        self.state.debouncer.stats = [[BounceStats::default(); 3]; 4];
+        #[cfg(feature = "bounce-capture")]
+        {
+            self.state.debouncer.captured = 0;
+        }
+    }
+
+    /// Reads the keypad once and returns the raw value along with what it decodes to, so
+    /// callers that need several views of the keypad don't have to read it several times.
+    pub fn scan_once(&mut self, delay: &mut dyn DelayMs<u16>) -> ScanSnapshot {
+        let raw = self.read(delay);
+        self.snapshot(raw)
+    }
+
+    // Describes a raw value read from the keypad
+    fn snapshot(&self, raw: u16) -> ScanSnapshot {
+        ScanSnapshot {
+            raw,
+            pressed: self.without_encoder(raw).count_ones() as u8,
+            key: self.decode(raw),
+        }
+    }
+
+    /**
+    Finds the shortest settle time that still reads the keypad correctly. Hold a key down
+    while this runs.
+
+    The keypad is first read with the configured settle time, then read again with
+    progressively shorter settle times until a read no longer matches. Each settle time is
+    sampled several times, so a setting only counts when all of its reads agree.
+    */
+    pub fn calibrate(&mut self, delay: &mut dyn DelayMs<u16>) -> CalibrationReport {
+        let raw = self.read(delay);
+        let mut min_settle_ms = None;
+
+        if raw != 0 {
+            for settle_ms in (0..=self.state.settle_ms).rev() {
+                if !self.is_stable(delay, settle_ms, raw) {
+                    break;
+                }
+                min_settle_ms = Some(settle_ms);
+            }
+        }
+
+        CalibrationReport {
+            raw,
+            scan_ms: self.scan_ms() as u16,
+            min_settle_ms,
+        }
+    }
+
+    /**
+    Returns the longest that a single read, such as [`read_char`](Self::read_char) or
+    [`poll`](Self::poll), can block, in microseconds. This is the time spent settling in one
+    scan, the settle time for each scanned column, times the most scans debouncing can take
+    before giving up, which is a few times the debounce samples when debouncing, times the
+    number of reads [`with_retries`](Self::with_retries) allows.
+
+    The time taken to drive and read the pins themselves isn't included, and neither are
+    [`calibrate`](Self::calibrate) and [`check_settle`](Self::check_settle), which read the
+    keypad many times over.
+    */
+    pub fn worst_case_scan_us(&self) -> u32 {
+        // A single sample always agrees with itself, so only longer runs can be cut short
+        let scans = match self.state.debouncer.samples {
+            1 => 1,
+            samples => samples.saturating_mul(DEBOUNCE_LIMIT),
+        };
+        let reads = u32::from(self.state.retries) + 1;
+        self.scan_ms()
+            .saturating_mul(u32::from(scans))
+            .saturating_mul(reads)
+            .saturating_mul(1000)
+    }
+
+    // Time spent settling during the scans of the last debounced read
+    fn read_ms(&self) -> u32 {
+        self.scan_ms() * u32::from(self.state.debouncer.last_scans.max(1))
+    }
+
+    // Time spent settling during one scan
+    fn scan_ms(&self) -> u32 {
+        let columns = self.state.active_columns.count_ones();
+        let mut settles = match self.state.scan_strategy {
+            ScanStrategy::DriveOne => columns,
+            // The read with every column driven settles first
+            ScanStrategy::ReleaseOne => columns + 1,
+        };
+        if self.state.charge_flush {
+            settles += columns;
+        }
+        u32::from(self.state.settle_ms) * settles
+    }
+
+    /// Checks whether the configured settle time looks long enough. Each column is read once
+    /// after settling, and again after settling for as long again. If any column changed in
+    /// between, the rows hadn't finished settling and this returns false. Keys shouldn't be
+    /// pressed or released while this runs, since that looks the same.
+    pub fn check_settle(&mut self, delay: &mut dyn DelayMs<u16>) -> bool {
+        let settle_ms = self.state.settle_ms;
+        let mut stable = true;
+
+        self.set_strobe(true);
+        for column in self.state.scan_order {
+            let column = usize::from(column);
+            if !self.select_column(column) {
+                continue;
+            }
+            delay.delay_ms(settle_ms);
+            let first = self.read_column();
+            delay.delay_ms(settle_ms);
+            stable &= self.read_column() == first;
         }
+        self.park();
+
+        stable
     }
 
     // Performs a "raw" read of the keypad and returns a bit set for each key down. Note,
     // this doesn't mean this code supports multiple key presses.
     fn read(&mut self, delay: &mut dyn DelayMs<u16>) -> u16 {
+        if self.state.suspended {
+            return self.remember(0);
+        }
+        loop {
+            let raw = self.read_with_settle(delay, self.state.settle_ms);
+            if let Some(stable) = self.state.debouncer.step(raw) {
+                return self.remember(stable);
+            }
+        }
+    }
+
+    // Keeps the key that a finished read decodes to for last_key, and returns the read's raw
+    // value
+    fn remember(&mut self, raw_value: u16) -> u16 {
+        let raw = self.strip_stray(raw_value);
+        self.state.last_key = Some(raw)
+            .filter(|raw| raw.count_ones() == 1)
+            .and_then(|raw| self.get_char(raw));
+        raw_value
+    }
+
+    // Reads the keypad and decodes the single key that's down, reading again up to the
+    // configured number of retries while more than one key is down
+    fn read_decoded(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<char> {
+        let mut raw = self.read(delay);
+        for _ in 0..self.state.retries {
+            if self.strip_stray(raw).count_ones() <= 1 {
+                break;
+            }
+            raw = self.read(delay);
+        }
+        self.decode(raw)
+    }
+
+    fn read_with_settle(&mut self, delay: &mut dyn DelayMs<u16>, settle_ms: u16) -> u16 {
+        self.scan(Some(delay), settle_ms)
+    }
+
+    /**
+    Performs a raw read of the keypad without waiting for the rows to settle after driving
+    each column, and returns a bit set for each key down.
+
+    This never blocks, but it's only reliable when the rows settle faster than the pins can be
+    read back to back, for example with short wiring and strong pull-ups. It's intended for
+    callers whose main loop is slow enough that the keypad is idle long between calls. If reads
+    are unreliable, use [`read_char`](Self::read_char) or [`calibrate`](Self::calibrate)
+    instead.
+    */
+    pub fn scan_no_delay(&mut self) -> u16 {
+        self.scan(None, 0)
+    }
+
+    // Drives each column in turn and assembles the raw value, waiting `settle_ms` after
+    // driving each column when there's a delay to wait with
+    fn scan(&mut self, mut delay: Option<&mut dyn DelayMs<u16>>, settle_ms: u16) -> u16 {
         let mut res = 0;
+        if self.state.suspended {
+            return res;
+        }
+        self.set_strobe(true);
+
+        let mut all_driven = 0;
+        if self.state.scan_strategy == ScanStrategy::ReleaseOne {
+            self.in_critical_section(|keypad| {
+                keypad.drive_columns(COLUMNS_RELEASED & !keypad.state.active_columns);
+                if let Some(delay) = delay.as_mut() {
+                    delay.delay_ms(settle_ms);
+                }
+                all_driven = keypad.read_column();
+            });
+        }
+        for column in self.state.scan_order {
+            let column = usize::from(column);
+            self.in_critical_section(|keypad| {
+                if keypad.state.charge_flush && keypad.is_scanned(column) {
+                    keypad.drive_columns(COLUMNS_RELEASED);
+                    if let Some(delay) = delay.as_mut() {
+                        delay.delay_ms(settle_ms);
+                    }
+                }
+                if !keypad.select_column_for_scan(column) {
+                    return;
+                }
+                if let Some(delay) = delay.as_mut() {
+                    delay.delay_ms(settle_ms);
+                }
+                res |= keypad.column_keys(all_driven) << (column * 4);
+            });
+        }
+        self.in_critical_section(Self::park);
+        self.complete_scan(res)
+    }
 
-        self.columns.0.set_low().unwrap_or_default();
-        res |= self.read_column(delay) << 0;
-        self.columns.0.set_high().unwrap_or_default();
+    // Runs `f` inside the critical section given to with_critical_section, if there is one
+    fn in_critical_section(&mut self, mut f: impl FnMut(&mut Self)) {
+        match self.state.critical_section {
+            Some(critical_section) => critical_section(&mut || f(self)),
+            None => f(self),
+        }
+    }
 
-        self.columns.1.set_low().unwrap_or_default();
-        res |= self.read_column(delay) << 4;
-        self.columns.1.set_high().unwrap_or_default();
+    // Drives a column so its rows can be read after settling, returning false without
+    // touching the pins when the column isn't being scanned
+    fn select_column(&mut self, column: usize) -> bool {
+        if !self.is_scanned(column) {
+            return false;
+        }
+        self.drive_columns(COLUMNS_RELEASED & !(1 << column));
+        true
+    }
 
-        self.columns.2.set_low().unwrap_or_default();
-        res |= self.read_column(delay) << 8;
-        self.columns.2.set_high().unwrap_or_default();
+    // Returns true for a column that's on the keypad and set to be scanned
+    fn is_scanned(&self, column: usize) -> bool {
+        column < COLS && self.state.active_columns & (1 << column) != 0
+    }
 
-        res
+    // Selects a column the way the scan strategy reads it: driven on its own, or released on
+    // its own with every other scanned column driven
+    fn select_column_for_scan(&mut self, column: usize) -> bool {
+        match self.state.scan_strategy {
+            ScanStrategy::DriveOne => self.select_column(column),
+            ScanStrategy::ReleaseOne => {
+                if !self.is_scanned(column) {
+                    return false;
+                }
+                let driven = self.state.active_columns & !(1 << column);
+                self.drive_columns(COLUMNS_RELEASED & !driven);
+                true
+            }
+        }
     }
 
-    // Converts the raw value from the read() method into a character that corresponds to the
-    // label on each key
-    fn get_char(&self, raw_value: u16) -> char {
-        let value = self.convert(raw_value);
-        match value {
-            -1 => '*',
-            -2 => '#',
-            _ => char::from_digit(value as u32, 10).unwrap(),
+    // Reads the rows with a key down in the column selected by select_column_for_scan, given
+    // the rows read with every column driven when the strategy needs them
+    fn column_keys(&self, all_driven: u16) -> u16 {
+        match self.state.scan_strategy {
+            ScanStrategy::DriveOne => self.read_column(),
+            ScanStrategy::ReleaseOne => all_driven & !self.read_column(),
         }
     }
 
-    fn read_column(&self, delay: &mut dyn DelayMs<u16>) -> u16 {
-        let mut res = 0;
+    // Writes a column pattern, with a set bit for each column that isn't selected, only
+    // touching the columns that changed once the current levels are known
+    fn drive_columns(&mut self, pattern: u8) {
+        let pattern = if self.state.inverted {
+            !pattern & COLUMNS_RELEASED
+        } else {
+            pattern
+        };
+        match self.state.column_pattern {
+            Some(previous) => self.columns.update_columns(previous, pattern),
+            None => self.columns.write_columns(pattern),
+        }
+        self.state.column_pattern = Some(pattern);
+    }
+
+    // Enables or disables the keypad with the strobe pin, if there is one
+    fn set_strobe(&mut self, enabled: bool) {
+        set_level(&mut self.strobe, enabled != self.state.strobe_active_low);
+    }
+
+    // Returns true when every calibration sample at this settle time reads `expected`
+    fn is_stable(&mut self, delay: &mut dyn DelayMs<u16>, settle_ms: u16, expected: u16) -> bool {
+        (0..CALIBRATION_SAMPLES).all(|_| self.read_with_settle(delay, settle_ms) == expected)
+    }
+
+    // Converts the raw value of a single key from the read() method into the character the
+    // keymap has for that key. This looks the key up without indexing, so a raw value with no
+    // keys, or a bit past the last key, gives None rather than panicking.
+    fn get_char(&self, raw_value: u16) -> Option<char> {
+        let (row, col) = bit_position(raw_value.trailing_zeros() as usize);
+        self.position_to_char(row, col)
+    }
+
+    // Drops the encoder's keys from a raw value, and the stray key when exactly two keys are
+    // down and only one of them is a tolerated stray position
+    fn strip_stray(&self, raw_value: u16) -> u16 {
+        let raw_value = self.without_encoder(raw_value);
+        let stray = raw_value & self.state.stray_mask;
+        if raw_value.count_ones() == 2 && stray.count_ones() == 1 {
+            raw_value & !stray
+        } else {
+            raw_value
+        }
+    }
+
+    // Drops the encoder's keys from a raw value
+    fn without_encoder(&self, raw_value: u16) -> u16 {
+        match &self.state.encoder {
+            Some(encoder) => raw_value & !(encoder.a | encoder.b),
+            None => raw_value,
+        }
+    }
+
+    // Returns the raw value with a bit set for each key in the keymap with one of these
+    // characters
+    fn keys_mask(&self, chars: &[char]) -> u16 {
+        chars
+            .iter()
+            .filter_map(|&c| self.char_to_position(c))
+            .fold(0, |mask, (row, col)| mask | key_mask(row, col))
+    }
+
+    // Converts a raw value into the character of its key, or None unless exactly one key is down
+    fn decode(&self, raw_value: u16) -> Option<char> {
+        let raw_value = self.single_key(self.strip_stray(raw_value));
+        if raw_value.count_ones() == 1 {
+            self.get_char(raw_value)
+        } else {
+            self.report_invalid(raw_value);
+            None
+        }
+    }
+
+    // Keeps only the lowest key of a raw value when the multi-key policy picks it
+    fn single_key(&self, raw_value: u16) -> u16 {
+        match self.state.multi_key_policy {
+            MultiKeyPolicy::Lowest => raw_value & raw_value.wrapping_neg(),
+            MultiKeyPolicy::Reject | MultiKeyPolicy::Error => raw_value,
+        }
+    }
 
-        delay.delay_ms(1u16);
-        if self.rows.0.is_low().unwrap_or_default() {
-            res |= 1 << 0;
+    // Passes a raw value with keys down that didn't decode to a single key to the on_invalid
+    // function
+    fn report_invalid(&self, raw_value: u16) {
+        match self.state.on_invalid {
+            Some(f) if raw_value != 0 => f(raw_value),
+            _ => {}
         }
-        if self.rows.1.is_low().unwrap_or_default() {
-            res |= 1 << 1;
+    }
+
+    // Passes the state of every key in a finished scan to the on_scan function, and returns
+    // the scan's raw value without any keys that are filtered out
+    fn complete_scan(&mut self, raw_value: u16) -> u16 {
+        self.state.unfiltered_raw = raw_value;
+        if let Some(f) = self.state.on_scan {
+            let mut matrix = [[false; COLS]; ROWS];
+            for (row, keys) in matrix.iter_mut().enumerate() {
+                for (col, pressed) in keys.iter_mut().enumerate() {
+                    *pressed = raw_value & key_mask(row, col) != 0;
+                }
+            }
+            f(&matrix);
         }
-        if self.rows.2.is_low().unwrap_or_default() {
-            res |= 1 << 2;
+
+        if self.state.digits_only {
+            raw_value & self.digit_keys_mask()
+        } else {
+            raw_value
         }
-        if self.rows.3.is_low().unwrap_or_default() {
-            res |= 1 << 3;
+    }
+
+    // Returns the raw value with a bit set for each key in the keymap with a digit
+    fn digit_keys_mask(&self) -> u16 {
+        let mut mask = 0;
+        for (row, keys) in self.state.keymap.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                if key.is_ascii_digit() {
+                    mask |= key_mask(row, col);
+                }
+            }
         }
+        mask
+    }
 
-        res
+    fn read_column(&self) -> u16 {
+        let low = self.rows.read_rows();
+        let pressed = low ^ self.state.active_high_rows;
+        u16::from(pressed & self.state.active_rows)
     }
 
     // Converts the raw value (2^N) from the read() method into a keypad digit. This will be
@@ -161,6 +2304,237 @@ impl<
     }
 }
 
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> KeypadRead for Keypad<R, C, B, S> {
+    fn read_key(&mut self, delay: &mut dyn DelayMs<u16>) -> Option<Key> {
+        self.read_decoded(delay).and_then(Key::from_char)
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Keypad<R, C, B, S> {
+    /**
+    Waits for the next key to be pressed or released. The keypad is scanned every
+    [`ASYNC_POLL_MS`] milliseconds, awaiting `delay` both between scans and while each column
+    settles, so other tasks keep running.
+
+    This shares its held-key state with [`poll`](Self::poll) and observes the same options.
+    */
+    pub async fn next_event<D: DelayNs>(&mut self, delay: &mut D) -> KeyEvent {
+        loop {
+            if self.state.suspended {
+                delay.delay_ms(ASYNC_POLL_MS).await;
+                continue;
+            }
+            let raw = self.read_async(delay).await;
+            let now_ms = self
+                .state
+                .clock_ms
+                .wrapping_add(self.read_ms() + ASYNC_POLL_MS);
+            self.state.clock_ms = now_ms;
+            if let Some(event) = self.update_held(raw, now_ms) {
+                return event.untimed();
+            }
+            delay.delay_ms(ASYNC_POLL_MS).await;
+        }
+    }
+
+    /// Same as [`scan_once`](Self::scan_once), but awaits `delay` while each column settles
+    pub async fn scan_once_async<D: DelayNs>(&mut self, delay: &mut D) -> ScanSnapshot {
+        let raw = self.read_async(delay).await;
+        self.snapshot(raw)
+    }
+
+    // Same as read(), but awaits the settle time
+    async fn read_async<D: DelayNs>(&mut self, delay: &mut D) -> u16 {
+        if self.state.suspended {
+            return self.remember(0);
+        }
+        loop {
+            let raw = self.scan_async(delay).await;
+            if let Some(stable) = self.state.debouncer.step(raw) {
+                return self.remember(stable);
+            }
+        }
+    }
+
+    // Same as scan(), but awaits the settle time
+    async fn scan_async<D: DelayNs>(&mut self, delay: &mut D) -> u16 {
+        let mut res = 0;
+        self.set_strobe(true);
+
+        let mut all_driven = 0;
+        if self.state.scan_strategy == ScanStrategy::ReleaseOne {
+            self.drive_columns(COLUMNS_RELEASED & !self.state.active_columns);
+            delay.delay_ms(u32::from(self.state.settle_ms)).await;
+            all_driven = self.read_column();
+        }
+        for column in self.state.scan_order {
+            let column = usize::from(column);
+            if self.state.charge_flush && self.is_scanned(column) {
+                self.drive_columns(COLUMNS_RELEASED);
+                delay.delay_ms(u32::from(self.state.settle_ms)).await;
+            }
+            if !self.select_column_for_scan(column) {
+                continue;
+            }
+            delay.delay_ms(u32::from(self.state.settle_ms)).await;
+            res |= self.column_keys(all_driven) << (column * 4);
+        }
+        self.park();
+        self.complete_scan(res)
+    }
+}
+
+/// How long [`Keypad::next_event`] waits between scans, in milliseconds
+#[cfg(feature = "async")]
+pub const ASYNC_POLL_MS: u32 = 10;
+
+/// Returns the character on the key at `row` and `col` of the standard telephone layout,
+/// counting from zero at the top left, or `None` if the position is off the keypad
+pub fn position_to_char(row: usize, col: usize) -> Option<char> {
+    DEFAULT_KEYMAP.get(row)?.get(col).copied()
+}
+
+/**
+Returns the key for a raw scan value, such as one from [`Keypad::scan_no_delay`], on the
+standard telephone layout, or `None` unless exactly one key is down. This uses nothing but the
+value, so scans captured from real hardware can be decoded in a test on the host.
+
+```
+# use keypad2::{decode, key_mask, Key};
+assert_eq!(decode(key_mask(0, 1)), Some(Key::Two));
+assert_eq!(decode(key_mask(0, 1) | key_mask(3, 2)), None);
+assert_eq!(decode(0), None);
+```
+*/
+pub fn decode(raw_value: u16) -> Option<Key> {
+    if raw_value.count_ones() != 1 {
+        return None;
+    }
+    let (row, col) = bit_position(raw_value.trailing_zeros() as usize);
+    position_to_char(row, col).and_then(Key::from_char)
+}
+
+/// Decodes each of a sequence of raw scan values with [`decode`], for replaying a log of scans
+/// captured from a keypad. Scans past the first `N` are left out.
+#[cfg(feature = "heapless")]
+pub fn decode_sequence<const N: usize>(raw_values: &[u16]) -> heapless::Vec<Option<Key>, N> {
+    raw_values
+        .iter()
+        .take(N)
+        .map(|&raw_value| decode(raw_value))
+        .collect()
+}
+
+/// Returns the `(row, col)` of the key with this character on the standard telephone layout,
+/// or `None` if no key has that character
+pub fn char_to_position(c: char) -> Option<(usize, usize)> {
+    keymap_position(&DEFAULT_KEYMAP, c)
+}
+
+/// Returns the bit that the key at `row` and `col` sets in a raw scan value. Raw values hold
+/// four bits per column, one per row, starting with column 0 in the lowest bits. A position
+/// off the keypad has no bit, so this returns 0 for it.
+pub const fn key_mask(row: usize, col: usize) -> u16 {
+    if row >= ROWS || col >= COLS {
+        return 0;
+    }
+    1 << (col * 4 + row)
+}
+
+/// Returns the `(row, col)` of the key for bit `bit` of a raw scan value
+pub const fn bit_position(bit: usize) -> (usize, usize) {
+    (bit % 4, bit / 4)
+}
+
+/// Returns the bits that the keys in `row` set in a raw scan value, or 0 for a row off the
+/// keypad
+pub const fn row_keys_mask(row: usize) -> u16 {
+    key_mask(row, 0) | key_mask(row, 1) | key_mask(row, 2)
+}
+
+/// Returns the bits that the keys in `col` set in a raw scan value, or 0 for a column off the
+/// keypad
+pub const fn column_keys_mask(col: usize) -> u16 {
+    if col >= COLS {
+        return 0;
+    }
+    ALL_ROWS << (col * 4)
+}
+
+/// Returns true when no character appears on more than one key of the keymap. This can check a
+/// keymap at compile time, with `const _: () = assert!(validate_keymap(&MY_KEYMAP));`
+pub const fn validate_keymap(keymap: &Keymap) -> bool {
+    find_duplicate(keymap).is_none()
+}
+
+// Returns the first character that appears on more than one key of the keymap
+const fn find_duplicate(keymap: &Keymap) -> Option<char> {
+    let mut i = 0;
+    while i < 12 {
+        let mut j = i + 1;
+        while j < 12 {
+            if keymap[i / 3][i % 3] == keymap[j / 3][j % 3] {
+                return Some(keymap[i / 3][i % 3]);
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+// Returns the scancode of each key in the keymap, or NO_CODE for keys whose characters don't
+// have one
+const fn keymap_codes(keymap: &Keymap) -> [[u8; 3]; 4] {
+    let mut codes = [[NO_CODE; 3]; 4];
+    let mut i = 0;
+    while i < 12 {
+        codes[i / 3][i % 3] = match keymap[i / 3][i % 3] {
+            c @ '0'..='9' => c as u8 - b'0',
+            '*' => 10,
+            '#' => 11,
+            _ => NO_CODE,
+        };
+        i += 1;
+    }
+    codes
+}
+
+// Returns the (row, col) of the key with this character in the keymap
+fn keymap_position(keymap: &Keymap, c: char) -> Option<(usize, usize)> {
+    keymap
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.iter().position(|&key| key == c).map(|col| (row, col)))
+}
+
+// Column pattern with every column driven high, so none are selected
+const COLUMNS_RELEASED: u8 = 0b111;
+
+// How many times the debounce samples a read can take before giving up on the keys settling
+const DEBOUNCE_LIMIT: u8 = 4;
+
+// Number of scans capture_bounce keeps
+#[cfg(feature = "bounce-capture")]
+const BOUNCE_CAPTURE: usize = 64;
+
+// Number of macros a keypad can hold
+const MACRO_SLOTS: usize = 4;
+
+// Code for keys without a scancode
+const NO_CODE: u8 = u8::MAX;
+
+// Steps an encoder moves by for each change of phase, indexed by the previous phase and then
+// the new one, with the phase holding the `a` line in bit 1 and the `b` line in bit 0
+const QUADRATURE_STEPS: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
+// Bits of a single column's read with every row pressed
+const ALL_ROWS: u16 = 0b1111;
+
+// Number of reads that must agree before calibration accepts a settle time
+const CALIBRATION_SAMPLES: u8 = 8;
+
 const KEY_1: u16 = 1;
 const KEY_4: u16 = 1 << 1;
 const KEY_7: u16 = 1 << 2;
@@ -0,0 +1,144 @@
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::{bit_position, key_mask, Keypad, PortColumns, RowPins, COLS, ROWS};
+
+/// A key pressed or released, found by debouncing each key separately
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellEvent {
+    /// The key at this position was pressed
+    Pressed {
+        /// Row of the key, counting from zero at the top
+        row: usize,
+        /// Column of the key, counting from zero at the left
+        col: usize,
+    },
+    /// The key at this position was released
+    Released {
+        /// Row of the key, counting from zero at the top
+        row: usize,
+        /// Column of the key, counting from zero at the left
+        col: usize,
+    },
+}
+
+impl CellEvent {
+    /// Returns the `(row, col)` of the key that was pressed or released
+    pub fn position(&self) -> (usize, usize) {
+        match *self {
+            CellEvent::Pressed { row, col } | CellEvent::Released { row, col } => (row, col),
+        }
+    }
+}
+
+/// The keys pressed or released by one scan, returned by [`Keypad::poll_matrix`]. Keys are
+/// listed a column at a time, from the top of each column.
+#[derive(Clone, Debug)]
+pub struct CellEvents {
+    changed: u16,
+    pressed: u16,
+}
+
+impl Iterator for CellEvents {
+    type Item = CellEvent;
+
+    fn next(&mut self) -> Option<CellEvent> {
+        if self.changed == 0 {
+            return None;
+        }
+        let bit = self.changed.trailing_zeros() as usize;
+        self.changed &= self.changed - 1;
+
+        let (row, col) = bit_position(bit);
+        if self.pressed & (1 << bit) != 0 {
+            Some(CellEvent::Pressed { row, col })
+        } else {
+            Some(CellEvent::Released { row, col })
+        }
+    }
+}
+
+// Debounces every key on its own, counting how many scans in a row each key has read
+// differently from its accepted state
+pub(crate) struct CellDebouncer {
+    counts: [[u8; COLS]; ROWS],
+    stable: u16,
+    released: u16,
+}
+
+impl CellDebouncer {
+    pub(crate) fn new() -> Self {
+        Self {
+            counts: [[0; COLS]; ROWS],
+            stable: 0,
+            released: 0,
+        }
+    }
+
+    // Releases every key that's down, keeping their releases for the next poll_matrix
+    pub(crate) fn release_all(&mut self) {
+        self.released |= self.stable;
+        self.stable = 0;
+        self.counts = [[0; COLS]; ROWS];
+    }
+
+    // Takes the raw value of one scan, returning the keys that changed once they've read the
+    // same for `samples` scans in a row
+    fn step(&mut self, raw: u16, samples: u8) -> CellEvents {
+        let mut changed = 0;
+        for (row, counts) in self.counts.iter_mut().enumerate() {
+            for (col, count) in counts.iter_mut().enumerate() {
+                let mask = key_mask(row, col);
+                if (raw ^ self.stable) & mask == 0 {
+                    *count = 0;
+                    continue;
+                }
+                *count = count.saturating_add(1);
+                if *count >= samples {
+                    *count = 0;
+                    changed |= mask;
+                }
+            }
+        }
+
+        self.stable ^= changed;
+        CellEvents {
+            changed,
+            pressed: self.stable,
+        }
+    }
+}
+
+impl<R: RowPins, C: PortColumns, B: OutputPin, S: OutputPin> Keypad<R, C, B, S> {
+    /**
+    Scans the keypad once and returns each key pressed or released, for reading chords and
+    other combinations of keys. Every key is debounced on its own, so a key only changes once
+    it has read the same for the number of scans set with
+    [`with_debounce_samples`](Keypad::with_debounce_samples), and holding one key doesn't
+    affect tapping another. Call this regularly, as each call does a single scan.
+
+    This keeps its own record of which keys are down, separate from [`poll`](Keypad::poll).
+    [`suspend`](Keypad::suspend) releases every key in it, and the next call reports those
+    releases without scanning, even while the keypad is suspended. Nothing else is reported
+    while the keypad is suspended.
+    */
+    pub fn poll_matrix(&mut self, delay: &mut dyn DelayMs<u16>) -> CellEvents {
+        let released = core::mem::replace(&mut self.state.cells.released, 0);
+        if released != 0 {
+            return CellEvents {
+                changed: released,
+                pressed: self.state.cells.stable,
+            };
+        }
+        if self.state.suspended {
+            return CellEvents {
+                changed: 0,
+                pressed: self.state.cells.stable,
+            };
+        }
+        let raw = self.scan(Some(delay), self.state.settle_ms);
+        let raw = self.without_encoder(raw);
+        let samples = self.state.debouncer.samples;
+        self.state.cells.step(raw, samples)
+    }
+}
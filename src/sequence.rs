@@ -0,0 +1,89 @@
+/**
+Watches the keys pressed for a code, such as the PIN of an alarm panel, along with an optional
+duress code that should be accepted the same way but trigger a different action
+
+Feed it each key as it's pressed. The codes can start at any point in the keys pressed, so a
+wrong key only costs the keys typed since the code last started.
+
+```
+# use keypad2::{Matched, Sequence};
+let mut sequence = Sequence::new(&['1', '2', '3', '4']).with_duress(&['1', '2', '3', '5']);
+
+assert_eq!(sequence.feed('9'), None);
+assert_eq!(sequence.feed('1'), None);
+assert_eq!(sequence.feed('2'), None);
+assert_eq!(sequence.feed('3'), None);
+assert_eq!(sequence.feed('5'), Some(Matched::Duress));
+```
+*/
+#[derive(Clone, Debug)]
+pub struct Sequence<'a> {
+    codes: [&'a [char]; 2],
+    progress: [usize; 2],
+}
+
+/// Which code a [`Sequence`] recognized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Matched {
+    /// The normal code
+    Primary,
+    /// The duress code
+    Duress,
+}
+
+impl<'a> Sequence<'a> {
+    /// Create a new instance that watches for `primary`. An empty code never matches.
+    pub const fn new(primary: &'a [char]) -> Self {
+        Self {
+            codes: [primary, &[]],
+            progress: [0; 2],
+        }
+    }
+
+    /// Also watches for `duress`, reported as [`Matched::Duress`]. When a key completes both
+    /// codes at once, the duress code wins.
+    pub const fn with_duress(mut self, duress: &'a [char]) -> Self {
+        self.codes[1] = duress;
+        self
+    }
+
+    /// Takes the next key pressed, returning which code it completed, if any. Completing a code
+    /// starts both codes over.
+    pub fn feed(&mut self, key: char) -> Option<Matched> {
+        for (code, progress) in self.codes.iter().zip(self.progress.iter_mut()) {
+            *progress = advance(code, *progress, key);
+        }
+
+        let matched = if is_complete(self.codes[1], self.progress[1]) {
+            Matched::Duress
+        } else if is_complete(self.codes[0], self.progress[0]) {
+            Matched::Primary
+        } else {
+            return None;
+        };
+        self.reset();
+        Some(matched)
+    }
+
+    /// Forgets the keys fed so far, for example after a timeout
+    pub fn reset(&mut self) {
+        self.progress = [0; 2];
+    }
+}
+
+fn is_complete(code: &[char], progress: usize) -> bool {
+    !code.is_empty() && progress == code.len()
+}
+
+// Returns how much of `code` is matched after `key`, given that the last `progress` keys matched
+// the start of it. On a mismatch this falls back to the longest start of the code that the keys
+// just typed still match.
+fn advance(code: &[char], progress: usize, key: char) -> usize {
+    if code.get(progress) == Some(&key) {
+        return progress + 1;
+    }
+    (1..=progress)
+        .rev()
+        .find(|&len| code[len - 1] == key && code[..len - 1] == code[progress + 1 - len..progress])
+        .unwrap_or(0)
+}
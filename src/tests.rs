@@ -0,0 +1,683 @@
+extern crate std;
+
+use core::cell::Cell;
+use std::rc::Rc;
+
+use embedded_hal::blocking::delay::DelayMs;
+
+use super::*;
+
+// The keys held down, as a raw value, and the pattern last written to the columns, shared by
+// the mock pins
+#[derive(Default)]
+struct Matrix {
+    held: Cell<u16>,
+    columns: Cell<u8>,
+}
+
+// Reads each row as low when a key in it is held in a column that's driven low
+struct MockRows(Rc<Matrix>);
+
+impl RowPins for MockRows {
+    fn read_rows(&self) -> u8 {
+        let held = self.0.held.get();
+        let driven = !self.0.columns.get() & COLUMNS_RELEASED;
+        let mut rows = 0;
+        for col in (0..COLS).filter(|col| driven & (1 << col) != 0) {
+            rows |= (held >> (col * 4)) as u8 & ALL_ROWS as u8;
+        }
+        rows
+    }
+}
+
+struct MockColumns(Rc<Matrix>);
+
+impl PortColumns for MockColumns {
+    fn write_columns(&mut self, pattern: u8) {
+        self.0.columns.set(pattern);
+    }
+}
+
+// Adds up the time spent waiting
+#[derive(Default)]
+struct MockDelay(u32);
+
+impl DelayMs<u16> for MockDelay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.0 += u32::from(ms);
+    }
+}
+
+fn keypad() -> (Rc<Matrix>, Keypad<MockRows, MockColumns>) {
+    let matrix = Rc::new(Matrix::default());
+    let keypad = Keypad::new(MockRows(matrix.clone()), MockColumns(matrix.clone()));
+    (matrix, keypad)
+}
+
+fn hold(matrix: &Matrix, keys: &[(usize, usize)]) {
+    let held = keys
+        .iter()
+        .fold(0, |held, &(row, col)| held | key_mask(row, col));
+    matrix.held.set(held);
+}
+
+#[test]
+fn tap_reports_press_and_release() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    assert_eq!(keypad.poll(&mut delay), None);
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(
+        keypad.poll(&mut delay),
+        Some(KeyEvent::Pressed {
+            key: '1',
+            time_ms: None
+        })
+    );
+    assert_eq!(keypad.poll(&mut delay), None);
+    hold(&matrix, &[]);
+    assert_eq!(
+        keypad.poll(&mut delay),
+        Some(KeyEvent::Released {
+            key: '1',
+            time_ms: None
+        })
+    );
+    assert_eq!(keypad.poll(&mut delay), None);
+}
+
+fn pressed(key: char, time_ms: u32) -> Option<KeyEvent> {
+    Some(KeyEvent::Pressed {
+        key,
+        time_ms: Some(time_ms),
+    })
+}
+
+fn released(key: char, time_ms: u32) -> Option<KeyEvent> {
+    Some(KeyEvent::Released {
+        key,
+        time_ms: Some(time_ms),
+    })
+}
+
+#[test]
+fn confirm_hold_waits_before_pressing() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_confirm_hold_ms(50);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(1, 1)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    assert_eq!(keypad.poll_at(&mut delay, 49), None);
+    assert_eq!(keypad.poll_at(&mut delay, 50), pressed('5', 50));
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 60), released('5', 60));
+}
+
+#[test]
+fn confirm_hold_drops_a_short_press() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_confirm_hold_ms(50);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(1, 1)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 20), None);
+    assert_eq!(keypad.poll_at(&mut delay, 100), None);
+}
+
+#[test]
+fn read_on_release_returns_the_key_when_let_go() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(2, 2)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_on_release(&mut delay), Some('9'));
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+}
+
+#[test]
+fn read_on_release_is_cancelled_by_sliding_off_the_key() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[(0, 1)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+
+    hold(&matrix, &[(0, 0), (1, 1)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_on_release(&mut delay), Some('1'));
+}
+
+#[test]
+fn scan_at_reads_one_column_per_settle_time() {
+    let (matrix, mut keypad) = keypad();
+
+    hold(&matrix, &[(1, 2)]);
+    assert_eq!(keypad.phase(), ScanPhase::Idle);
+    assert_eq!(keypad.scan_at(0), None);
+    assert_eq!(keypad.phase(), ScanPhase::Settling(0));
+    assert_eq!(matrix.columns.get(), 0b110);
+
+    assert_eq!(keypad.scan_at(0), None);
+    assert_eq!(keypad.phase(), ScanPhase::Settling(0));
+    assert_eq!(keypad.scan_at(1), None);
+    assert_eq!(keypad.phase(), ScanPhase::Settling(1));
+    assert_eq!(matrix.columns.get(), 0b101);
+    assert_eq!(keypad.scan_at(2), None);
+    assert_eq!(keypad.phase(), ScanPhase::Settling(2));
+
+    assert_eq!(keypad.scan_at(3), pressed('6', 3));
+    assert_eq!(keypad.phase(), ScanPhase::Idle);
+    assert_eq!(matrix.columns.get(), COLUMNS_RELEASED);
+}
+
+#[test]
+fn quick_repress_is_suppressed() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_min_repress_ms(50);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), pressed('1', 0));
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 10), released('1', 10));
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 20), None);
+    assert_eq!(keypad.poll_at(&mut delay, 80), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 90), None);
+
+    hold(&matrix, &[(0, 1)]);
+    assert_eq!(keypad.poll_at(&mut delay, 100), pressed('2', 100));
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 110), released('2', 110));
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 200), pressed('1', 200));
+}
+
+#[test]
+fn debouncer_waits_for_agreeing_samples() {
+    let mut debouncer = Debouncer::new();
+    debouncer.samples = 3;
+
+    assert_eq!(debouncer.step(1), None);
+    assert_eq!(debouncer.step(0), None);
+    assert_eq!(debouncer.step(1), None);
+    assert_eq!(debouncer.step(1), None);
+    assert_eq!(debouncer.step(1), Some(1));
+    assert_eq!(debouncer.last_scans, 5);
+    assert_eq!(debouncer.stats[0][0].last, 5);
+}
+
+#[test]
+fn debouncer_gives_up_on_a_value_that_never_settles() {
+    let mut debouncer = Debouncer::new();
+    debouncer.samples = 2;
+
+    let limit = usize::from(2 * DEBOUNCE_LIMIT);
+    for scan in 1..limit {
+        assert_eq!(debouncer.step(1 << (scan % 2)), None);
+    }
+    assert_eq!(debouncer.step(1 << (limit % 2)), Some(0));
+}
+
+#[test]
+fn poll_counts_every_debounce_scan_as_time() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_debounce_samples(3).with_confirm_hold_ms(30);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll(&mut delay), None);
+    let held_from_ms = delay.0;
+    while keypad.poll(&mut delay).is_none() {}
+    let held_ms = delay.0 - held_from_ms;
+    assert!((30..39).contains(&held_ms), "held for {}ms", held_ms);
+}
+
+#[test]
+fn scan_at_debounces_complete_scans() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_debounce_samples(2);
+
+    hold(&matrix, &[(0, 0)]);
+    for now_ms in 0..3 {
+        assert_eq!(keypad.scan_at(now_ms), None);
+    }
+    assert_eq!(keypad.scan_at(3), None);
+    assert_eq!(keypad.phase(), ScanPhase::Idle);
+    for now_ms in 4..7 {
+        assert_eq!(keypad.scan_at(now_ms), None);
+    }
+    assert_eq!(keypad.scan_at(7), pressed('1', 7));
+}
+
+#[test]
+fn poll_matrix_debounces_each_key_on_its_own() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_debounce_samples(2);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_matrix(&mut delay).next(), None);
+    hold(&matrix, &[(0, 0), (3, 2)]);
+    assert!(keypad
+        .poll_matrix(&mut delay)
+        .eq([CellEvent::Pressed { row: 0, col: 0 }]));
+    assert!(keypad
+        .poll_matrix(&mut delay)
+        .eq([CellEvent::Pressed { row: 3, col: 2 }]));
+
+    hold(&matrix, &[(3, 2)]);
+    assert_eq!(keypad.poll_matrix(&mut delay).next(), None);
+    hold(&matrix, &[(0, 0), (3, 2)]);
+    assert_eq!(keypad.poll_matrix(&mut delay).next(), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_matrix(&mut delay).next(), None);
+    assert!(keypad.poll_matrix(&mut delay).eq([
+        CellEvent::Released { row: 0, col: 0 },
+        CellEvent::Released { row: 3, col: 2 },
+    ]));
+}
+
+#[test]
+fn encoder_counts_steps_in_either_direction() {
+    let mut encoder = Encoder {
+        a: 1,
+        b: 2,
+        phase: None,
+        steps: 0,
+    };
+
+    for raw in [0, 1, 3, 2, 0] {
+        encoder.step(raw);
+    }
+    assert_eq!(encoder.take_steps(), Some(4));
+    assert_eq!(encoder.take_steps(), None);
+
+    for raw in [2, 3, 1, 0] {
+        encoder.step(raw);
+    }
+    assert_eq!(encoder.take_steps(), Some(-4));
+}
+
+#[test]
+fn poll_reports_encoder_turns_instead_of_its_keys() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_encoder('7', '*');
+    let mut delay = MockDelay::default();
+
+    assert_eq!(keypad.poll(&mut delay), None);
+    hold(&matrix, &[(2, 0)]);
+    assert_eq!(keypad.poll(&mut delay), Some(KeyEvent::Rotate(1)));
+    hold(&matrix, &[(2, 0), (3, 0)]);
+    assert_eq!(keypad.poll(&mut delay), Some(KeyEvent::Rotate(1)));
+    hold(&matrix, &[(3, 0)]);
+    assert_eq!(keypad.poll(&mut delay), Some(KeyEvent::Rotate(1)));
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll(&mut delay), Some(KeyEvent::Rotate(1)));
+}
+
+#[test]
+fn read_on_release_ignores_a_closed_encoder_line() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_encoder('7', '*');
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(2, 0), (1, 1)]);
+    assert_eq!(keypad.read_on_release(&mut delay), None);
+    hold(&matrix, &[(2, 0)]);
+    assert_eq!(keypad.read_on_release(&mut delay), Some('5'));
+}
+
+#[test]
+fn shorted_column_is_found_through_the_digits_only_filter() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_digits_only_filter(true);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0), (1, 0), (2, 0), (3, 0)]);
+    assert_eq!(
+        keypad.try_read_char(&mut delay),
+        Err(Error::ShortedColumn(0))
+    );
+    hold(&matrix, &[(3, 0)]);
+    assert_eq!(keypad.try_read_char(&mut delay), Ok(None));
+}
+
+#[test]
+fn shorted_column_is_found_with_fewer_active_rows() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_active_rows(0b0111);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 2), (1, 2), (2, 2), (3, 2)]);
+    assert_eq!(
+        keypad.try_read_char(&mut delay),
+        Err(Error::ShortedColumn(2))
+    );
+    hold(&matrix, &[]);
+    assert_eq!(keypad.try_read_char(&mut delay), Ok(None));
+}
+
+// Records the columns written before each wait
+struct ColumnsDuringDelay(Rc<Matrix>, std::vec::Vec<u8>);
+
+impl DelayMs<u16> for ColumnsDuringDelay {
+    fn delay_ms(&mut self, _ms: u16) {
+        self.1.push(self.0.columns.get());
+    }
+}
+
+#[test]
+fn charge_flush_waits_with_every_column_released() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_settle_ms(2).with_charge_flush(true);
+    let mut delay = ColumnsDuringDelay(matrix.clone(), std::vec::Vec::new());
+
+    hold(&matrix, &[(1, 2)]);
+    assert_eq!(keypad.read_char(&mut delay), '6');
+    assert_eq!(delay.1, [0b111, 0b110, 0b111, 0b101, 0b111, 0b011]);
+    assert_eq!(keypad.worst_case_scan_us(), 12000);
+}
+
+#[test]
+fn lock_on_first_keeps_the_first_key_through_a_second() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_lock_on_first(true);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), pressed('1', 0));
+    hold(&matrix, &[(0, 0), (0, 1)]);
+    assert_eq!(keypad.poll_at(&mut delay, 10), None);
+    hold(&matrix, &[(0, 1)]);
+    assert_eq!(keypad.poll_at(&mut delay, 20), released('1', 20));
+    assert_eq!(keypad.poll_at(&mut delay, 30), pressed('2', 30));
+}
+
+#[test]
+fn macro_plays_its_expansion_back_one_read_at_a_time() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad
+        .with_macro('#', &['1', '2', '3'])
+        .with_macro('*', &[]);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(3, 2)]);
+    assert_eq!(keypad.read_char(&mut delay), '1');
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_char(&mut delay), '2');
+    assert_eq!(keypad.read_char(&mut delay), '3');
+    assert_eq!(keypad.read_char(&mut delay), ' ');
+
+    hold(&matrix, &[(3, 0)]);
+    assert_eq!(keypad.read_char(&mut delay), ' ');
+    hold(&matrix, &[(1, 0)]);
+    assert_eq!(keypad.read_char(&mut delay), '4');
+}
+
+#[test]
+fn macro_for_the_same_trigger_replaces_the_old_one() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_macro('#', &['1']).with_macro('#', &['9']);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(3, 2)]);
+    assert_eq!(keypad.read_char(&mut delay), '9');
+}
+
+#[test]
+fn demux_columns_select_one_column_at_a_time() {
+    let matrix = Rc::new(Matrix::default());
+    let selected = Rc::new(core::cell::RefCell::new(std::vec::Vec::new()));
+    let columns = {
+        let matrix = matrix.clone();
+        let selected = selected.clone();
+        DemuxColumns::new(move |column: Option<usize>| {
+            selected.borrow_mut().push(column);
+            let pattern = column.map_or(COLUMNS_RELEASED, |column| !(1 << column));
+            matrix.columns.set(pattern & COLUMNS_RELEASED);
+        })
+    };
+    let mut keypad = Keypad::new(MockRows(matrix.clone()), columns);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(2, 1)]);
+    assert_eq!(keypad.read_char(&mut delay), '8');
+    assert_eq!(*selected.borrow(), [Some(0), Some(1), Some(2), None]);
+
+    selected.borrow_mut().clear();
+    keypad.arm_wake();
+    assert_eq!(*selected.borrow(), [None]);
+}
+
+#[test]
+fn release_one_reads_a_single_key() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_scan_strategy(ScanStrategy::ReleaseOne);
+    let mut delay = MockDelay::default();
+
+    for (row, col) in [(0, 0), (2, 1), (3, 2)] {
+        hold(&matrix, &[(row, col)]);
+        assert_eq!(
+            keypad.read_char(&mut delay),
+            keypad.position_to_char(row, col).unwrap()
+        );
+    }
+    hold(&matrix, &[]);
+    assert_eq!(keypad.read_char(&mut delay), ' ');
+}
+
+#[test]
+fn release_one_misses_keys_sharing_a_row() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_scan_strategy(ScanStrategy::ReleaseOne);
+
+    hold(&matrix, &[(0, 0), (0, 1)]);
+    assert_eq!(keypad.scan_no_delay(), 0);
+    hold(&matrix, &[(0, 0), (1, 1)]);
+    assert_eq!(keypad.scan_no_delay(), key_mask(0, 0) | key_mask(1, 1));
+}
+
+#[test]
+fn release_one_timing_includes_the_all_driven_read() {
+    let (_matrix, keypad) = keypad();
+    let mut keypad = keypad
+        .with_settle_ms(1)
+        .with_scan_strategy(ScanStrategy::ReleaseOne);
+    let mut delay = MockDelay::default();
+
+    keypad.read_char(&mut delay);
+    assert_eq!(delay.0, 4);
+    assert_eq!(keypad.worst_case_scan_us(), 4000);
+}
+
+#[test]
+fn longpress_map_tap_reports_the_key_on_release() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_longpress_map(100, &[('1', 'A')]);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    assert_eq!(keypad.poll_at(&mut delay, 50), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 60), pressed('1', 60));
+    assert_eq!(keypad.poll_at(&mut delay, 70), released('1', 60));
+    assert_eq!(keypad.poll_at(&mut delay, 80), None);
+}
+
+#[test]
+fn longpress_map_hold_reports_the_substitute() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_longpress_map(100, &[('1', 'A')]);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    assert_eq!(keypad.poll_at(&mut delay, 99), None);
+    assert_eq!(keypad.poll_at(&mut delay, 100), pressed('A', 100));
+    assert_eq!(keypad.poll_at(&mut delay, 150), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 160), released('A', 160));
+}
+
+#[test]
+fn suspend_releases_the_held_key() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), pressed('1', 0));
+    assert_eq!(
+        keypad.suspend(),
+        Some(KeyEvent::Released {
+            key: '1',
+            time_ms: None
+        })
+    );
+    assert_eq!(keypad.poll_at(&mut delay, 10), None);
+    keypad.resume();
+    assert_eq!(keypad.poll_at(&mut delay, 20), pressed('1', 20));
+}
+
+#[test]
+fn suspend_with_a_longpress_pending_reports_nothing() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_longpress_map(100, &[('1', 'A')]);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    assert_eq!(keypad.suspend(), None);
+    keypad.resume();
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 50), None);
+}
+
+#[test]
+fn suspend_returns_a_queued_release() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_longpress_map(100, &[('1', 'A')]);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    assert_eq!(keypad.poll_at(&mut delay, 0), None);
+    hold(&matrix, &[]);
+    assert_eq!(keypad.poll_at(&mut delay, 10), pressed('1', 10));
+    assert_eq!(
+        keypad.suspend(),
+        Some(KeyEvent::Released {
+            key: '1',
+            time_ms: None
+        })
+    );
+    keypad.resume();
+    assert_eq!(keypad.poll_at(&mut delay, 20), None);
+}
+
+#[test]
+fn suspend_releases_the_keys_poll_matrix_holds() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0), (2, 1)]);
+    assert_eq!(keypad.poll_matrix(&mut delay).count(), 2);
+    keypad.suspend();
+    assert!(keypad.poll_matrix(&mut delay).eq([
+        CellEvent::Released { row: 0, col: 0 },
+        CellEvent::Released { row: 2, col: 1 },
+    ]));
+    assert_eq!(keypad.poll_matrix(&mut delay).next(), None);
+
+    keypad.resume();
+    hold(&matrix, &[(2, 1)]);
+    assert!(keypad
+        .poll_matrix(&mut delay)
+        .eq([CellEvent::Pressed { row: 2, col: 1 }]));
+}
+
+#[test]
+fn multi_key_policy_reject_reads_no_key() {
+    let (matrix, mut keypad) = keypad();
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(1, 1), (0, 2)]);
+    assert_eq!(keypad.read_char(&mut delay), ' ');
+    assert_eq!(keypad.try_read_char(&mut delay), Ok(None));
+    assert_eq!(keypad.poll(&mut delay), None);
+}
+
+#[test]
+fn multi_key_policy_lowest_reads_the_lowest_column_first() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_multi_key_policy(MultiKeyPolicy::Lowest);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 2), (1, 1)]);
+    assert_eq!(keypad.read_char(&mut delay), '5');
+    assert_eq!(keypad.read_u8(&mut delay), Some(5));
+    assert_eq!(keypad.try_read_char(&mut delay), Ok(Some('5')));
+    assert_eq!(
+        keypad.poll(&mut delay),
+        Some(KeyEvent::Pressed {
+            key: '5',
+            time_ms: None
+        })
+    );
+}
+
+#[test]
+fn multi_key_policy_error_is_reported_by_try_read_char() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_multi_key_policy(MultiKeyPolicy::Error);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(1, 1), (0, 2)]);
+    let raw = key_mask(1, 1) | key_mask(0, 2);
+    assert_eq!(
+        keypad.try_read_char(&mut delay),
+        Err(Error::MultipleKeys(raw))
+    );
+    assert_eq!(keypad.read_char(&mut delay), ' ');
+    hold(&matrix, &[(1, 1)]);
+    assert_eq!(keypad.try_read_char(&mut delay), Ok(Some('5')));
+}
+
+#[test]
+fn events_time_out_even_without_settle_time() {
+    let (matrix, keypad) = keypad();
+    let mut keypad = keypad.with_settle_ms(0);
+    let mut delay = MockDelay::default();
+
+    hold(&matrix, &[(0, 0)]);
+    let mut events = keypad.events(&mut delay).with_idle_timeout_ms(10);
+    assert_eq!(
+        events.next(),
+        Some(KeyEvent::Pressed {
+            key: '1',
+            time_ms: None
+        })
+    );
+    assert_eq!(events.next(), None);
+}